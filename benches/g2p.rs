@@ -0,0 +1,60 @@
+//! 对比每次重建资源和复用`G2p`引擎时单次文本转音素的耗时
+//!
+//! 之前的`g2p`函数在每次调用时编译正则表达式并创建分词器，cmudict和espeak的词典则是全局加载一次的，
+//! “rebuild per call”一组在每次调用前重建同样的资源，以此模拟之前的开销。
+//!
+//! 运行：`cargo bench --bench g2p`
+
+use jieba_rs::Jieba;
+use kokoro_tts::{EnglishVariant, G2PError, G2p};
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+const TEXTS: [&str; 3] = [
+    "您好，请问有什么可以帮您？",
+    "Hello, world!你好，我们是一群追逐梦想的人。",
+    "The meeting starts at 10 o'clock.",
+];
+const ROUNDS: u32 = 20;
+
+fn measure<F>(name: &str, mut f: F) -> Result<Duration, G2PError>
+where
+    F: FnMut(&str, bool) -> Result<String, G2PError>,
+{
+    let mut total = Duration::ZERO;
+    for use_v11 in [false, true] {
+        for text in TEXTS {
+            for _ in 0..ROUNDS {
+                let t = Instant::now();
+                std::hint::black_box(f(text, use_v11)?);
+                total += t.elapsed();
+            }
+        }
+    }
+    let per_call = total / (ROUNDS * TEXTS.len() as u32 * 2);
+    println!("{:<24}{:>12.3?} / call", name, per_call);
+    Ok(per_call)
+}
+
+fn main() -> Result<(), G2PError> {
+    // 两组测量使用同一个引擎完成转换，只比较每次调用前重建资源的开销
+    let engine = G2p::new()?;
+
+    let before = measure("rebuild per call", |text, use_v11| {
+        std::hint::black_box((
+            Regex::new(r#"\d+(\.\d+)?"#)?,
+            Regex::new(
+                r#"([\u4E00-\u9FFF]+)|([，。：·？、！《》（）【】〖〗〔〕“”‘’〈〉…—　]+)|([\u0000-\u00FF]+)+"#,
+            )?,
+            Regex::new("\\w+|\\W+")?,
+            Jieba::new(),
+        ));
+        engine.g2p(text, use_v11, EnglishVariant::American)
+    })?;
+    let after = measure("reused engine", |text, use_v11| {
+        engine.g2p(text, use_v11, EnglishVariant::American)
+    })?;
+    println!("speedup: {:.1}x", before.as_secs_f64() / after.as_secs_f64());
+
+    Ok(())
+}
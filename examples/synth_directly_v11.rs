@@ -7,7 +7,7 @@ async fn main() -> anyhow::Result<()> {
     let (audio, took) = tts
         .synth(
             "Hello, world!你好，我们是一群追逐梦想的人。我正在使用qq。",
-            Voice::Zm045(1),
+            Voice::Zm045(1.),
         )
         .await?;
     println!("Synth took: {:?}", took);
@@ -1,22 +1,24 @@
 use futures::StreamExt;
-use kokoro_tts::{KokoroTts, Voice};
+use kokoro_tts::{KokoroTts, Pauses, Voice};
 use rodio::{OutputStreamBuilder, Sink, buffer::SamplesBuffer};
 use std::sync::Arc;
 use tokio::time::{Duration, sleep};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let tts = KokoroTts::new("kokoro-v1.1-zh.onnx", "voices-v1.1-zh.bin").await?;
-    let (mut sink, mut stream) = tts.stream(Voice::Zm098(1));
+    let tts = KokoroTts::new("kokoro-v1.1-zh.onnx", "voices-v1.1-zh.bin")
+        .await?
+        .with_pauses(Pauses::default());
+    let (mut sink, mut stream) = tts.stream(Voice::Zm098(1.));
     sink.synth("hello world.").await?;
     sink.synth("你好，我们是一群追逐梦想的人。").await?;
-    sink.set_voice(Voice::Zf032(2));
+    sink.set_voice(Voice::Zf032(2.));
     sink.synth("我正在使用qq。").await?;
-    sink.set_voice(Voice::Zf090(3));
+    sink.set_voice(Voice::Zf090(3.));
     sink.synth("今天天气如何？").await?;
-    sink.set_voice(Voice::Zm045(1));
+    sink.set_voice(Voice::Zm045(1.));
     sink.synth("你在使用Rust编程语言吗？").await?;
-    sink.set_voice(Voice::Zf039(1));
+    sink.set_voice(Voice::Zf039(1.));
     sink.synth(
         "你轻轻地走过那
 在风雨花丛中
@@ -27,7 +29,7 @@ async fn main() -> anyhow::Result<()> {
 在那彩虹 最温柔的风",
     )
     .await?;
-    sink.set_voice(Voice::Zf088(1));
+    sink.set_voice(Voice::Zf088(1.));
     sink.synth(
         "你静静看着我们
 最不舍的面容
@@ -49,6 +51,9 @@ async fn main() -> anyhow::Result<()> {
             player.append(SamplesBuffer::new(1, 24000, audio));
             println!("Synth took: {:?}", took);
         }
+        if let Some(e) = stream.take_error() {
+            eprintln!("Synthesis failed: {}", e);
+        }
     });
 
     sleep(Duration::from_secs(20)).await;
@@ -0,0 +1,221 @@
+#ifndef KOKORO_H
+#define KOKORO_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * C接口的返回状态
+ */
+typedef enum KokoroStatus {
+  /**
+   * 成功
+   */
+  KOKORO_STATUS_OK = 0,
+  /**
+   * 合成失败或库内部出错，通过`kokoro_last_error`获取错误信息
+   */
+  KOKORO_STATUS_ERROR = 1,
+  /**
+   * 参数为空指针或不是有效的UTF-8字符串
+   */
+  KOKORO_STATUS_INVALID_ARGUMENT = 2,
+  /**
+   * 缓冲区不足，所需的采样数已写入`len`
+   */
+  KOKORO_STATUS_BUFFER_TOO_SMALL = 3,
+} KokoroStatus;
+
+/**
+ * 语音合成引擎
+ */
+typedef struct KokoroEngine KokoroEngine;
+
+/**
+ * 流式合成会话，音频在后台线程中合成并回调
+ */
+typedef struct KokoroStream KokoroStream;
+
+/**
+ * 接收音频的回调函数，`samples`只在回调期间有效
+ */
+typedef void (*KokoroAudioCallback)(const float *samples, size_t len, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * 获取当前线程最后一次失败的错误信息，没有错误时返回空指针
+ *
+ * 返回的字符串在当前线程下一次调用失败之前有效。
+ */
+const char *kokoro_last_error(void);
+
+/**
+ * 合成音频的采样率
+ */
+uint32_t kokoro_sample_rate(void);
+
+/**
+ * 从模型文件和发音人文件创建引擎，失败时返回空指针
+ *
+ * # Safety
+ *
+ * `model_path`和`voices_path`必须是以`\0`结尾的字符串。
+ */
+struct KokoroEngine *kokoro_engine_new(const char *model_path, const char *voices_path);
+
+/**
+ * 从内存中的模型和发音人数据创建引擎，失败时返回空指针
+ *
+ * # Safety
+ *
+ * `model`和`voices`必须分别指向长度为`model_len`和`voices_len`的有效内存，函数返回后即可释放。
+ */
+struct KokoroEngine *kokoro_engine_new_from_bytes(const uint8_t *model,
+                                                  size_t model_len,
+                                                  const uint8_t *voices,
+                                                  size_t voices_len);
+
+/**
+ * 释放引擎，所有流式合成会话必须已经结束
+ *
+ * # Safety
+ *
+ * `engine`必须是`kokoro_engine_new`系列函数返回的指针或空指针，释放后不能再使用。
+ */
+void kokoro_engine_free(struct KokoroEngine *engine);
+
+/**
+ * 发音人数量
+ *
+ * # Safety
+ *
+ * `engine`必须是有效的引擎指针。
+ */
+size_t kokoro_voice_count(const struct KokoroEngine *engine);
+
+/**
+ * 按名称排序后第`index`个发音人的名称，越界时返回空指针
+ *
+ * 返回的字符串在引擎释放之前有效。
+ *
+ * # Safety
+ *
+ * `engine`必须是有效的引擎指针。
+ */
+const char *kokoro_voice_name(const struct KokoroEngine *engine, size_t index);
+
+/**
+ * 合成语音并写入调用方提供的缓冲区
+ *
+ * 成功时`len`为写入的采样数；缓冲区不足时返回`KOKORO_STATUS_BUFFER_TOO_SMALL`，`len`为所需的采样数。
+ * 合成结果不会保留，事先不知道长度时使用`kokoro_synth_alloc`可以避免重复合成，较长的文本建议使用`kokoro_synth_with_callback`。
+ *
+ * # Safety
+ *
+ * `engine`必须是有效的引擎指针，`text`和`voice`必须是以`\0`结尾的字符串，
+ * `buffer`必须指向至少`capacity`个`float`，`len`必须是有效的指针。
+ */
+enum KokoroStatus kokoro_synth(struct KokoroEngine *engine,
+                               const char *text,
+                               const char *voice,
+                               float speed,
+                               float *buffer,
+                               size_t capacity,
+                               size_t *len);
+
+/**
+ * 合成语音并返回由库分配的缓冲区
+ *
+ * 成功时`samples`指向合成的音频，`len`为采样数，使用完后必须通过`kokoro_audio_free`释放。
+ *
+ * # Safety
+ *
+ * `engine`必须是有效的引擎指针，`text`和`voice`必须是以`\0`结尾的字符串，`samples`和`len`必须是有效的指针。
+ */
+enum KokoroStatus kokoro_synth_alloc(struct KokoroEngine *engine,
+                                     const char *text,
+                                     const char *voice,
+                                     float speed,
+                                     float **samples,
+                                     size_t *len);
+
+/**
+ * 释放`kokoro_synth_alloc`返回的音频
+ *
+ * # Safety
+ *
+ * `samples`和`len`必须是`kokoro_synth_alloc`返回的值，或者`samples`为空指针；释放后不能再使用。
+ */
+void kokoro_audio_free(float *samples,
+                       size_t len);
+
+/**
+ * 逐句合成语音，每合成完一句就调用一次`callback`，所有句子合成完后返回
+ *
+ * # Safety
+ *
+ * `engine`必须是有效的引擎指针，`text`和`voice`必须是以`\0`结尾的字符串。
+ */
+enum KokoroStatus kokoro_synth_with_callback(struct KokoroEngine *engine,
+                                             const char *text,
+                                             const char *voice,
+                                             float speed,
+                                             KokoroAudioCallback callback,
+                                             void *user_data);
+
+/**
+ * 开始流式合成会话，失败时返回空指针
+ *
+ * 通过`kokoro_stream_synth`发送的文本按顺序合成，`callback`在会话的后台线程中被调用。
+ *
+ * # Safety
+ *
+ * `engine`必须是有效的引擎指针，并且在会话结束之前不能释放；`voice`必须是以`\0`结尾的字符串；
+ * `user_data`必须可以在其他线程中使用。
+ */
+struct KokoroStream *kokoro_stream_start(struct KokoroEngine *engine,
+                                         const char *voice,
+                                         float speed,
+                                         KokoroAudioCallback callback,
+                                         void *user_data);
+
+/**
+ * 向流式合成会话发送一段文本
+ *
+ * # Safety
+ *
+ * `stream`必须是有效的会话指针，`text`必须是以`\0`结尾的字符串。
+ */
+enum KokoroStatus kokoro_stream_synth(struct KokoroStream *stream, const char *text);
+
+/**
+ * 等待已发送的文本全部合成并回调完毕，然后释放会话
+ *
+ * 某段文本合成失败时之后的文本不再合成，返回`KOKORO_STATUS_ERROR`。
+ *
+ * # Safety
+ *
+ * `stream`必须是有效的会话指针，释放后不能再使用；不能在回调函数中调用。
+ */
+enum KokoroStatus kokoro_stream_finish(struct KokoroStream *stream);
+
+/**
+ * 立即停止流式合成并释放会话，函数返回后不会再调用回调函数
+ *
+ * # Safety
+ *
+ * `stream`必须是有效的会话指针，释放后不能再使用；不能在回调函数中调用。
+ */
+void kokoro_stream_stop(struct KokoroStream *stream);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KOKORO_H */
@@ -0,0 +1,605 @@
+use {crate::KokoroError, std::time::Duration};
+
+/// 模型输出音频的采样率
+pub const SAMPLE_RATE: u32 = 24000;
+
+/// 时长对应的采样数
+pub(super) fn to_samples(duration: Duration) -> usize {
+    (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as usize
+}
+
+/// 采样数对应的时长
+pub(super) fn from_samples(samples: usize) -> Duration {
+    Duration::from_secs_f64(samples as f64 / SAMPLE_RATE as f64)
+}
+
+/// 使用线性插值对单声道音频重新采样
+///
+/// # 参数
+///
+/// * `samples` - 音频采样。
+/// * `from` - 原采样率。
+/// * `to` - 目标采样率。
+pub fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() || to == 0 {
+        return samples.to_vec();
+    }
+    let len = (samples.len() as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let j = pos as usize;
+            let frac = (pos - j as f64) as f32;
+            let a = samples[j.min(samples.len() - 1)];
+            let b = samples[(j + 1).min(samples.len() - 1)];
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// 使用WSOLA算法改变单声道音频的速度，音高保持不变
+///
+/// # 参数
+///
+/// * `samples` - 音频采样。
+/// * `rate` - 速度倍数，大于1时加快，小于1时放慢。
+pub fn time_stretch(samples: &[f32], rate: f32) -> Vec<f32> {
+    // 20毫秒的帧，50%重叠，在±5毫秒内寻找最相似的位置
+    const FRAME: usize = SAMPLE_RATE as usize / 50;
+    const HOP: usize = FRAME / 2;
+    const TOLERANCE: usize = SAMPLE_RATE as usize / 200;
+
+    if !rate.is_finite() || rate <= 0. || (rate - 1.).abs() < 1e-3 || samples.len() < FRAME {
+        return samples.to_vec();
+    }
+    let window = (0..FRAME)
+        .map(|i| 0.5 - 0.5 * (2. * std::f32::consts::PI * i as f32 / FRAME as f32).cos())
+        .collect::<Vec<_>>();
+    let len = (samples.len() as f64 / rate as f64).round() as usize;
+    let mut out = vec![0.; len + FRAME];
+    let mut norm = vec![0.; len + FRAME];
+
+    let mut prev: Option<usize> = None;
+    for k in 0.. {
+        let out_pos = k * HOP;
+        let nominal = (out_pos as f64 * rate as f64).round() as usize;
+        if out_pos >= len || nominal + FRAME > samples.len() {
+            break;
+        }
+        // 选择与上一帧自然延续最相似的位置，避免相位不连续
+        let start = match prev {
+            Some(p) if p + HOP + FRAME <= samples.len() => {
+                let target = &samples[p + HOP..p + HOP + FRAME];
+                let low = nominal.saturating_sub(TOLERANCE);
+                let high = (nominal + TOLERANCE).min(samples.len() - FRAME);
+                (low..=high)
+                    .map(|s| {
+                        let corr = samples[s..s + FRAME]
+                            .iter()
+                            .zip(target)
+                            .map(|(a, b)| a * b)
+                            .sum::<f32>();
+                        (s, corr)
+                    })
+                    .fold((nominal, f32::MIN), |a, b| if b.1 > a.1 { b } else { a })
+                    .0
+            }
+            _ => nominal,
+        };
+        for (i, w) in window.iter().enumerate() {
+            out[out_pos + i] += samples[start + i] * w;
+            norm[out_pos + i] += w;
+        }
+        prev = Some(start);
+    }
+
+    out.truncate(len);
+    out.iter_mut()
+        .zip(norm)
+        .for_each(|(s, n)| *s = if n > 1e-3 { *s / n } else { 0. });
+    out
+}
+
+/// 调整到目标时长的音频，参见[`crate::KokoroTts::synth_to_duration`]
+#[derive(Clone, Debug)]
+pub struct FittedAudio {
+    /// 音频采样
+    pub audio: Vec<f32>,
+    /// 推理耗时
+    pub took: Duration,
+    /// 重新合成时的语速相对于发音人原语速的倍数，未重新合成时为1
+    pub speed: f32,
+    /// 合成后再进行时间拉伸的速度倍数，未拉伸时为1
+    pub stretch: f32,
+    /// 时长是否在容差以内，需要的变化超出语速和拉伸的调整范围时为`false`
+    pub fits: bool,
+}
+
+impl FittedAudio {
+    /// 总的速度变化倍数，大于1表示加快
+    pub fn rate(&self) -> f32 {
+        self.speed * self.stretch
+    }
+}
+
+/// 把已合成的音频调整到目标采样数
+///
+/// 超出容差时先调用`synth`按比例调整语速重新合成，仍然超出容差时再进行时间拉伸，语速和拉伸的倍数都限制在0.5到2之间。
+///
+/// # 参数
+///
+/// * `synthesized` - 按原语速合成的音频和推理耗时。
+/// * `target_len` - 目标采样数。
+/// * `synth` - 按语速倍数重新合成。
+pub(super) async fn fit_to_length<F, R>(
+    (mut audio, mut took): (Vec<f32>, Duration),
+    target_len: usize,
+    synth: F,
+) -> Result<FittedAudio, KokoroError>
+where
+    F: FnOnce(f32) -> R,
+    R: Future<Output = Result<(Vec<f32>, Duration), KokoroError>>,
+{
+    // 时长相差在2%以内时不再调整
+    const TOLERANCE: f32 = 0.02;
+    const MIN_RATE: f32 = 0.5;
+    const MAX_RATE: f32 = 2.;
+
+    let within =
+        |len: usize| len == target_len || (len as f32 / target_len as f32 - 1.).abs() <= TOLERANCE;
+    let (mut speed, mut stretch) = (1., 1.);
+    if target_len > 0 && !audio.is_empty() {
+        if !within(audio.len()) {
+            // 时长与语速近似成反比
+            speed = (audio.len() as f32 / target_len as f32).clamp(MIN_RATE, MAX_RATE);
+            let (a, t) = synth(speed).await?;
+            audio = a;
+            took += t;
+        }
+        if !audio.is_empty() && !within(audio.len()) {
+            stretch = (audio.len() as f32 / target_len as f32).clamp(MIN_RATE, MAX_RATE);
+            audio = time_stretch(&audio, stretch);
+        }
+    }
+
+    Ok(FittedAudio {
+        fits: within(audio.len()),
+        audio,
+        took,
+        speed,
+        stretch,
+    })
+}
+
+/// 将音频转换为16位有符号小端序的PCM数据
+pub fn to_pcm_s16le(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|i| ((i.clamp(-1., 1.) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+/// 16位单声道WAV文件头
+///
+/// # 参数
+///
+/// * `sample_rate` - 采样率。
+/// * `data_len` - PCM数据的字节数，流式输出时长度未知，可以传入`u32::MAX`。
+pub fn wav_header(sample_rate: u32, data_len: u32) -> Vec<u8> {
+    wav_header_with_channels(sample_rate, 1, data_len)
+}
+
+fn wav_header_with_channels(sample_rate: u32, channels: u16, data_len: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    header.extend_from_slice(&(channels * 2).to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// 将音频编码为16位单声道WAV文件
+pub fn to_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data = to_pcm_s16le(samples);
+    let mut wav = wav_header(sample_rate, data.len() as u32);
+    wav.extend_from_slice(&data);
+    wav
+}
+
+/// 将左右声道编码为16位立体声WAV文件，较短的声道在末尾补静音
+pub fn to_wav_stereo(left: &[f32], right: &[f32], sample_rate: u32) -> Vec<u8> {
+    let len = left.len().max(right.len());
+    let samples = (0..len)
+        .flat_map(|i| {
+            [
+                left.get(i).copied().unwrap_or_default(),
+                right.get(i).copied().unwrap_or_default(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let data = to_pcm_s16le(&samples);
+    let mut wav = wav_header_with_channels(sample_rate, 2, data.len() as u32);
+    wav.extend_from_slice(&data);
+    wav
+}
+
+/// FLAC每一帧的采样数
+const FLAC_BLOCK_SIZE: usize = 4096;
+
+struct BitWriter {
+    data: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            acc: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.acc = (self.acc << 1) | ((value >> i) & 1);
+            self.bits += 1;
+            if self.bits == 8 {
+                self.data.push(self.acc as u8);
+                self.acc = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    fn write_unary(&mut self, zeros: u64) {
+        for _ in 0..zeros {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+    }
+
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, b| {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// 固定阶数预测的残差
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len())
+        .map(|i| {
+            let s = |j: usize| samples[i - j];
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+/// 选出编码残差所需位数最少的Rice参数
+fn rice_parameter(residual: &[i32]) -> (u32, u64) {
+    (0..15)
+        .map(|k| {
+            let bits = residual
+                .iter()
+                .map(|r| (((*r << 1) ^ (*r >> 31)) as u32 as u64 >> k) + 1 + k as u64)
+                .sum();
+            (k, bits)
+        })
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap_or_default()
+}
+
+/// 16位单声道FLAC编码器
+///
+/// 先输出[`FlacEncoder::header`]，然后可以分多次调用[`FlacEncoder::encode`]流式地输出音频帧，最后调用[`FlacEncoder::finish`]输出剩余的采样。
+pub struct FlacEncoder {
+    sample_rate: u32,
+    pending: Vec<i32>,
+    frame_number: u64,
+}
+
+impl FlacEncoder {
+    /// 创建编码器
+    ///
+    /// # 参数
+    ///
+    /// * `sample_rate` - 采样率。
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            pending: Vec::with_capacity(FLAC_BLOCK_SIZE),
+            frame_number: 0,
+        }
+    }
+
+    /// FLAC文件头
+    ///
+    /// # 参数
+    ///
+    /// * `total_samples` - 采样总数，流式输出时长度未知，可以传入0。
+    pub fn header(&self, total_samples: u64) -> Vec<u8> {
+        let mut w = BitWriter::new();
+        w.write(u32::from_be_bytes(*b"fLaC") as u64, 32);
+        // STREAMINFO，并且是最后一个元数据块
+        w.write(1, 1);
+        w.write(0, 7);
+        w.write(34, 24);
+        w.write(FLAC_BLOCK_SIZE as u64, 16);
+        w.write(FLAC_BLOCK_SIZE as u64, 16);
+        w.write(0, 24);
+        w.write(0, 24);
+        w.write(self.sample_rate as u64, 20);
+        w.write(0, 3); // 单声道
+        w.write(15, 5); // 16位
+        w.write(total_samples, 36);
+        w.write(0, 64); // 未计算MD5
+        w.write(0, 64);
+        w.data
+    }
+
+    /// 编码音频，凑满一帧的采样会被输出，其余的采样留到下次调用
+    pub fn encode(&mut self, samples: &[f32]) -> Vec<u8> {
+        let mut ret = Vec::new();
+        for i in samples {
+            self.pending
+                .push((i.clamp(-1., 1.) * i16::MAX as f32) as i16 as i32);
+            if self.pending.len() == FLAC_BLOCK_SIZE {
+                ret.extend(self.frame());
+            }
+        }
+        ret
+    }
+
+    /// 输出剩余的采样
+    pub fn finish(&mut self) -> Vec<u8> {
+        if self.pending.is_empty() {
+            Vec::new()
+        } else {
+            self.frame()
+        }
+    }
+
+    fn frame(&mut self) -> Vec<u8> {
+        let block = std::mem::take(&mut self.pending);
+        let mut w = BitWriter::new();
+        w.write(0xFFF8, 16); // 同步码，固定块大小
+        w.write(0b0111, 4); // 块大小在帧头末尾用16位表示
+        w.write(0, 4); // 采样率与STREAMINFO相同
+        w.write(0, 4); // 单声道
+        w.write(0b100, 3); // 16位
+        w.write(0, 1);
+        // 帧号使用UTF-8的方式编码
+        let n = self.frame_number;
+        let len = match n {
+            0..0x80 => 1,
+            0x80..0x800 => 2,
+            0x800..0x10000 => 3,
+            0x10000..0x200000 => 4,
+            0x200000..0x4000000 => 5,
+            0x4000000..0x80000000 => 6,
+            _ => 7,
+        };
+        if len == 1 {
+            w.write(n, 8);
+        } else {
+            w.write((0xFF00 >> len) & 0xFF | n >> (6 * (len - 1)), 8);
+            for i in (0..len - 1).rev() {
+                w.write(0x80 | ((n >> (6 * i)) & 0x3F), 8);
+            }
+        }
+        w.write(block.len() as u64 - 1, 16);
+        let crc = crc8(&w.data);
+        w.write(crc as u64, 8);
+
+        // 子帧：选出残差最小的固定阶数预测，残差太大时直接存储原始采样
+        w.write(0, 1);
+        let best = (0..=4.min(block.len() - 1))
+            .map(|order| {
+                let residual = fixed_residual(&block, order);
+                let (k, bits) = rice_parameter(&residual);
+                (order, residual, k, bits + 16 * order as u64 + 10)
+            })
+            .min_by_key(|i| i.3);
+        match best {
+            Some((order, residual, k, bits)) if bits < 16 * block.len() as u64 => {
+                w.write(0b001000 | order as u64, 6);
+                w.write(0, 1);
+                for i in &block[..order] {
+                    w.write(*i as u16 as u64, 16);
+                }
+                w.write(0, 2); // 4位Rice参数
+                w.write(0, 4); // 只有一个分区
+                w.write(k as u64, 4);
+                for r in residual {
+                    let u = ((r << 1) ^ (r >> 31)) as u32 as u64;
+                    w.write_unary(u >> k);
+                    w.write(u & ((1 << k) - 1), k);
+                }
+            }
+            _ => {
+                w.write(0b000001, 6);
+                w.write(0, 1);
+                for i in &block {
+                    w.write(*i as u16 as u64, 16);
+                }
+            }
+        }
+        w.align();
+        let crc = crc16(&w.data);
+        w.write(crc as u64, 16);
+
+        self.frame_number += 1;
+        w.data
+    }
+}
+
+/// 将音频编码为16位单声道FLAC文件
+pub fn to_flac(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let mut encoder = FlacEncoder::new(sample_rate);
+    let mut flac = encoder.header(samples.len() as u64);
+    flac.extend(encoder.encode(samples));
+    flac.extend(encoder.finish());
+    flac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_stretch() {
+        let sine = (0..SAMPLE_RATE)
+            .map(|i| (2. * std::f32::consts::PI * 220. * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect::<Vec<_>>();
+        let crossings = |s: &[f32]| s.windows(2).filter(|w| w[0] < 0. && w[1] >= 0.).count();
+
+        for rate in [0.85, 1.15, 2.5] {
+            let stretched = time_stretch(&sine, rate);
+            let seconds = stretched.len() as f32 / SAMPLE_RATE as f32;
+            assert!((seconds - 1. / rate).abs() < 0.01);
+            // 音高不变，每秒的周期数仍约为220
+            let pitch = crossings(&stretched) as f32 / seconds;
+            assert!((pitch - 220.).abs() < 10., "{} at rate {}", pitch, rate);
+        }
+        assert_eq!(sine, time_stretch(&sine, 1.));
+    }
+
+    #[test]
+    fn test_to_flac() -> Result<(), claxon::Error> {
+        // 正弦、静音、超出范围的采样和伪随机噪声，帧数超过128使帧号需要多字节编码，最后一帧不满
+        let mut seed = 1u32;
+        let samples = (0..130 * FLAC_BLOCK_SIZE + 1000)
+            .map(|i| match i / FLAC_BLOCK_SIZE % 4 {
+                0 => (i as f32 * 0.05).sin() * 0.8,
+                1 => 0.,
+                2 => (i as f32 * 0.01).sin() * 1.5,
+                _ => {
+                    seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                    (seed >> 8) as f32 / (1 << 23) as f32 - 1.
+                }
+            })
+            .collect::<Vec<_>>();
+        let expected = samples
+            .iter()
+            .map(|i| (i.clamp(-1., 1.) * i16::MAX as f32) as i16 as i32)
+            .collect::<Vec<_>>();
+
+        let flac = to_flac(&samples, SAMPLE_RATE);
+        let mut reader = claxon::FlacReader::new(flac.as_slice())?;
+        assert_eq!(SAMPLE_RATE, reader.streaminfo().sample_rate);
+        assert_eq!(Some(samples.len() as u64), reader.streaminfo().samples);
+        let decoded = reader.samples().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(expected, decoded);
+
+        // 流式输出时分多次编码，结果相同
+        let mut encoder = FlacEncoder::new(SAMPLE_RATE);
+        let mut flac = encoder.header(0);
+        for chunk in samples.chunks(3000) {
+            flac.extend(encoder.encode(chunk));
+        }
+        flac.extend(encoder.finish());
+        let mut reader = claxon::FlacReader::new(flac.as_slice())?;
+        let decoded = reader.samples().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(expected, decoded);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fit_to_length() -> Result<(), KokoroError> {
+        // 模拟的合成：`len`是原语速下的采样数，实际时长与语速成反比再乘以`bias`
+        let synth = |len: usize, bias: f32| {
+            move |speed: f32| async move {
+                let len = (len as f32 / speed * bias) as usize;
+                Ok((vec![0.; len], Duration::from_millis(10)))
+            }
+        };
+        let fit = |len: usize, target: usize, bias: f32| {
+            fit_to_length(
+                (vec![0.; len], Duration::from_millis(10)),
+                target,
+                synth(len, bias),
+            )
+        };
+
+        // 在容差以内，不重新合成
+        let fitted = fit_to_length((vec![0.; 24000], Duration::ZERO), 24200, |_| async {
+            Err(KokoroError::VoiceNotFound("unexpected".to_owned()))
+        })
+        .await?;
+        assert_eq!((1., 1., true), (fitted.speed, fitted.stretch, fitted.fits));
+        assert_eq!(24000, fitted.audio.len());
+
+        // 调整语速就够了
+        let fitted = fit(24000, 12000, 1.).await?;
+        assert_eq!((2., 1., true), (fitted.speed, fitted.stretch, fitted.fits));
+        assert_eq!(12000, fitted.audio.len());
+        assert_eq!(Duration::from_millis(20), fitted.took);
+
+        // 语速调整不到位时再拉伸
+        let fitted = fit(24000, 16000, 1.2).await?;
+        assert_eq!(1.5, fitted.speed);
+        assert!((fitted.stretch - 1.2).abs() < 1e-3);
+        assert!(fitted.fits);
+        assert_eq!(16000, fitted.audio.len());
+
+        // 需要的变化超出范围时语速和拉伸都停在上限，并报告无法对齐
+        let fitted = fit(96000, 6000, 1.).await?;
+        assert_eq!((2., 2., false), (fitted.speed, fitted.stretch, fitted.fits));
+        assert_eq!(4., fitted.rate());
+        assert_eq!(24000, fitted.audio.len());
+
+        let fitted = fit(6000, 96000, 1.).await?;
+        assert_eq!(
+            (0.5, 0.5, false),
+            (fitted.speed, fitted.stretch, fitted.fits)
+        );
+        assert_eq!(24000, fitted.audio.len());
+        Ok(())
+    }
+}
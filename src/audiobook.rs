@@ -0,0 +1,629 @@
+//! 长文本的有声书合成
+
+#[cfg(feature = "epub")]
+mod epub;
+
+use {
+    crate::{
+        Cue, KokoroError, KokoroTts, Pauses, SAMPLE_RATE, Voice,
+        audio::{from_samples, to_samples},
+        cache::Fnv,
+        split_sentences, to_srt, to_wav,
+    },
+    regex::Regex,
+    serde_json::{Value, json},
+    std::{
+        fs::{create_dir_all, read, read_to_string, rename, write},
+        mem::take,
+        path::{Path, PathBuf},
+        sync::LazyLock,
+        time::Duration,
+    },
+};
+
+/// 纯文本中单独成行的章节标题
+static CHAPTER_HEADING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(第[0-9零一二三四五六七八九十百千两〇]+[章回节卷部篇集]|(?i:chapter|part)\s+[0-9ivxlc]+\b|序章|序言|楔子|引子|前言|尾声|后记)",
+    )
+    .unwrap()
+});
+static MD_IMAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap());
+static MD_LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+static MD_MARKUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]+>|[*_`~]+").unwrap());
+static MD_LIST: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([-*+]|\d+[.)])\s+").unwrap());
+
+/// 单句超过该字数时在分句处继续切分，避免超出模型的输入长度
+const MAX_CHUNK_CHARS: usize = 200;
+
+/// 章节中的一段内容
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    /// 标题
+    Heading(String),
+    /// 段落
+    Paragraph(String),
+}
+
+/// 章节
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Chapter {
+    /// 章节标题，没有标题时为空
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+/// 拆分为章节和段落的书
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    /// 书名，未知时为空
+    pub title: String,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Book {
+    /// 根据扩展名读取纯文本、Markdown（.md、.markdown）或EPUB（.epub，需要启用`epub`特性）文件
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, KokoroError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|i| i.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("md" | "markdown") => Ok(Self::from_markdown(&read_to_string(path)?)),
+            #[cfg(feature = "epub")]
+            Some("epub") => Self::from_epub(&read(path)?),
+            #[cfg(not(feature = "epub"))]
+            Some("epub") => Err(KokoroError::DocumentInvalid(
+                "EPUB support requires the epub feature".to_owned(),
+            )),
+            _ => Ok(Self::from_text(&read_to_string(path)?)),
+        }
+    }
+
+    /// 解析纯文本
+    ///
+    /// 以“第一章”、“Chapter 1”等开头的短行作为章节标题。有空行时以空行分段，否则每一行是一段。
+    pub fn from_text(text: &str) -> Self {
+        let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let blank_lines = text.contains("\n\n");
+        let mut book = Self::default();
+        let mut paragraph = String::new();
+        for line in text.lines().map(str::trim) {
+            if line.chars().count() < 50 && CHAPTER_HEADING.is_match(line) {
+                book.paragraph(take(&mut paragraph));
+                book.heading(line.to_owned(), true);
+            } else if line.is_empty() || !blank_lines {
+                book.paragraph(take(&mut paragraph));
+                join_line(&mut paragraph, line);
+            } else {
+                join_line(&mut paragraph, line);
+            }
+        }
+        book.paragraph(paragraph);
+        book.finish()
+    }
+
+    /// 解析Markdown
+    ///
+    /// 最高一级的标题作为章节标题；如果最高一级只有开头的一个标题，它会作为书名，下一级标题作为章节标题。代码块会被忽略。
+    pub fn from_markdown(text: &str) -> Self {
+        let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let mut headings = Vec::new();
+        let mut code = false;
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|i| {
+                if i.starts_with("```") || i.starts_with("~~~") {
+                    code = !code;
+                    return false;
+                }
+                !code
+            })
+            .map(|i| {
+                let level = i.chars().take_while(|c| *c == '#').count();
+                let heading = (1..=6).contains(&level) && i[level..].starts_with(' ');
+                if heading {
+                    headings.push(level);
+                }
+                (heading.then_some(level), i)
+            })
+            .collect::<Vec<_>>();
+
+        let mut book = Self::default();
+        let top = headings.iter().min().copied().unwrap_or(1);
+        let mut chapter_level = top;
+        let mut title_level = None;
+        // 只有开头的一个最高级标题时，它是书名，次一级的标题是章节
+        if headings.first() == Some(&top)
+            && headings.iter().filter(|i| **i == top).count() == 1
+            && let Some(next) = headings.iter().filter(|i| **i > top).min()
+        {
+            chapter_level = *next;
+            title_level = Some(top);
+        }
+
+        let mut paragraph = String::new();
+        for (level, line) in lines {
+            if let Some(level) = level {
+                book.paragraph(take(&mut paragraph));
+                let heading =
+                    md_inline(line[level..].trim_matches(|c: char| c == '#' || c.is_whitespace()));
+                if Some(level) == title_level {
+                    book.title = heading;
+                } else {
+                    book.heading(heading, level <= chapter_level);
+                }
+            } else if line
+                .chars()
+                .all(|c| matches!(c, '-' | '*' | '_' | '=' | '|' | ':' | ' '))
+            {
+                book.paragraph(take(&mut paragraph));
+            } else if let Some(item) = MD_LIST.find(line) {
+                book.paragraph(take(&mut paragraph));
+                join_line(&mut paragraph, &md_inline(&line[item.end()..]));
+            } else {
+                let line = line
+                    .trim_start_matches('>')
+                    .trim_matches('|')
+                    .replace('|', " ");
+                join_line(&mut paragraph, &md_inline(&line));
+            }
+        }
+        book.paragraph(paragraph);
+        book.finish()
+    }
+
+    /// 句子总数，即合成时的进度总数
+    pub fn sentences(&self) -> usize {
+        self.chapters
+            .iter()
+            .flat_map(|i| &i.blocks)
+            .map(|i| chunk_sentences(i.text()).len())
+            .sum()
+    }
+
+    /// 开始新的章节，当前章节还没有内容时沿用当前章节
+    fn start_chapter(&mut self) {
+        if self.chapters.last().is_none_or(|i| !i.blocks.is_empty()) {
+            self.chapters.push(Chapter::default());
+        }
+    }
+
+    fn current(&mut self) -> &mut Chapter {
+        if self.chapters.is_empty() {
+            self.chapters.push(Chapter::default());
+        }
+        let last = self.chapters.len() - 1;
+        &mut self.chapters[last]
+    }
+
+    fn heading(&mut self, text: String, new_chapter: bool) {
+        if text.is_empty() {
+            return;
+        }
+        if new_chapter {
+            self.start_chapter();
+        }
+        let chapter = self.current();
+        if chapter.title.is_empty() {
+            chapter.title = text.clone();
+        }
+        chapter.blocks.push(Block::Heading(text));
+    }
+
+    fn paragraph(&mut self, text: String) {
+        let text = text.trim();
+        if !text.is_empty() {
+            self.current()
+                .blocks
+                .push(Block::Paragraph(text.to_owned()));
+        }
+    }
+
+    fn finish(mut self) -> Self {
+        self.chapters.retain(|i| !i.blocks.is_empty());
+        self
+    }
+}
+
+impl Block {
+    /// 内容的文本
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Heading(text) | Self::Paragraph(text) => text,
+        }
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{2E80}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FFEF}')
+}
+
+/// 把一行接到段落后面，中日韩文字之间不加空格
+pub(super) fn join_line(paragraph: &mut String, line: &str) {
+    let line = line.trim();
+    if line.is_empty() {
+        return;
+    }
+    if let (Some(a), Some(b)) = (paragraph.chars().last(), line.chars().next())
+        && !is_cjk(a)
+        && !is_cjk(b)
+    {
+        paragraph.push(' ');
+    }
+    paragraph.push_str(line);
+}
+
+/// 去掉Markdown的行内标记
+fn md_inline(text: &str) -> String {
+    let text = MD_IMAGE.replace_all(text, "");
+    let text = MD_LINK.replace_all(&text, "$1");
+    MD_MARKUP.replace_all(&text, "").trim().to_owned()
+}
+
+/// 把段落切分为适合一次合成的句子
+fn chunk_sentences(text: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    for sentence in split_sentences(text) {
+        let mut chunk = String::new();
+        let mut len = 0;
+        for c in sentence.chars() {
+            chunk.push(c);
+            len += 1;
+            let clause = matches!(c, '，' | ',' | '、' | '：' | ':' | '—');
+            if (clause && len >= MAX_CHUNK_CHARS / 2)
+                || (c.is_whitespace() && len >= MAX_CHUNK_CHARS)
+                || len >= MAX_CHUNK_CHARS * 3 / 2
+            {
+                ret.push(take(&mut chunk).trim().to_owned());
+                len = 0;
+            }
+        }
+        ret.push(chunk.trim().to_owned());
+    }
+    ret.retain(|i| i.chars().any(char::is_alphanumeric));
+    ret
+}
+
+/// 有声书合成的设置
+#[derive(Clone, Debug)]
+pub struct AudiobookOptions {
+    /// 句子之间的停顿
+    pub sentence_pause: Duration,
+    /// 段落之后的停顿
+    pub paragraph_pause: Duration,
+    /// 标题之后的停顿
+    pub heading_pause: Duration,
+}
+
+impl Default for AudiobookOptions {
+    fn default() -> Self {
+        Self {
+            sentence_pause: Duration::from_millis(100),
+            paragraph_pause: Duration::from_millis(600),
+            heading_pause: Duration::from_secs(1),
+        }
+    }
+}
+
+/// 有声书的合成进度
+#[derive(Clone, Debug)]
+pub struct Progress {
+    /// 正在合成的章节序号，从0开始
+    pub chapter: usize,
+    /// 章节总数
+    pub chapters: usize,
+    /// 全书已完成的句子数，包括从检查点恢复的章节
+    pub done: usize,
+    /// 全书的句子总数
+    pub total: usize,
+}
+
+/// 有声书索引中的一个章节
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChapterEntry {
+    /// 章节标题
+    pub title: String,
+    /// 音频文件名
+    pub audio: PathBuf,
+    /// 逐句对齐的SRT字幕文件名
+    pub captions: PathBuf,
+    /// 音频时长
+    pub duration: Duration,
+    /// 章节内容、模型和影响音频的设置的指纹，与当前不同时该章节不能恢复
+    pub fingerprint: u64,
+}
+
+impl ChapterEntry {
+    fn to_json(&self) -> Value {
+        json!({
+            "title": self.title,
+            "audio": self.audio,
+            "captions": self.captions,
+            "duration": self.duration.as_secs_f64(),
+            "fingerprint": format!("{:016x}", self.fingerprint),
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            title: value["title"].as_str()?.to_owned(),
+            audio: value["audio"].as_str()?.into(),
+            captions: value["captions"].as_str()?.into(),
+            duration: Duration::try_from_secs_f64(value["duration"].as_f64()?).ok()?,
+            fingerprint: u64::from_str_radix(value["fingerprint"].as_str()?, 16).ok()?,
+        })
+    }
+}
+
+/// 计算章节的指纹
+///
+/// # 参数
+///
+/// * `base` - 模型、音色文件和词表等设置的指纹。
+/// * `pauses` - 标点和换行处的停顿。
+/// * `chapter` - 章节。
+/// * `options` - 有声书的停顿设置。
+fn chapter_fingerprint(
+    base: u64,
+    pauses: Option<&Pauses>,
+    chapter: &Chapter,
+    options: &AudiobookOptions,
+) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(&base.to_le_bytes());
+    let pauses = pauses.map(|p| [p.comma, p.clause, p.sentence, p.line_break, p.paragraph]);
+    let durations = [
+        options.sentence_pause,
+        options.paragraph_pause,
+        options.heading_pause,
+    ];
+    for i in pauses.into_iter().flatten().chain(durations) {
+        hasher.write(&i.as_nanos().to_le_bytes());
+    }
+    hasher.write(chapter.title.as_bytes());
+    for block in &chapter.blocks {
+        let (kind, text) = match block {
+            Block::Heading(text) => (0, text),
+            Block::Paragraph(text) => (1, text),
+        };
+        hasher.write(&[0, kind]);
+        hasher.write(text.as_bytes());
+    }
+    hasher.finish()
+}
+
+const INDEX_FILE: &str = "index.json";
+
+/// 读取检查点，书名、发音人或语速不同时不能恢复，各章节还需要比较指纹
+fn read_index(dir: &Path, book: &Book, voice: Voice) -> Vec<ChapterEntry> {
+    let Some(index) = read(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|i| serde_json::from_slice::<Value>(&i).ok())
+    else {
+        return Vec::new();
+    };
+    if index["title"] != book.title
+        || index["voice"] != voice.name()
+        || index["speed"].as_f64() != Some(voice.speed() as f64)
+    {
+        return Vec::new();
+    }
+    index["chapters"]
+        .as_array()
+        .map(|i| i.iter().map_while(ChapterEntry::from_json).collect())
+        .unwrap_or_default()
+}
+
+/// 先写入临时文件再改名，避免中断时留下不完整的文件
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), KokoroError> {
+    let tmp = path.with_extension("tmp");
+    write(&tmp, data)?;
+    rename(tmp, path)?;
+    Ok(())
+}
+
+pub(super) async fn synth_audiobook<F>(
+    tts: &KokoroTts,
+    book: &Book,
+    voice: Voice,
+    options: &AudiobookOptions,
+    dir: &Path,
+    mut progress: F,
+) -> Result<Vec<ChapterEntry>, KokoroError>
+where
+    F: FnMut(&Progress),
+{
+    create_dir_all(dir)?;
+    let mut entries = read_index(dir, book, voice);
+    let mut state = Progress {
+        chapter: 0,
+        chapters: book.chapters.len(),
+        done: 0,
+        total: book.sentences(),
+    };
+
+    for (index, chapter) in book.chapters.iter().enumerate() {
+        state.chapter = index;
+        let name = format!("{:03}", index + 1);
+        let fingerprint = chapter_fingerprint(
+            tts.settings.fingerprint,
+            tts.pauses.as_ref(),
+            chapter,
+            options,
+        );
+        let resumed = entries.get(index).is_some_and(|i| {
+            i.fingerprint == fingerprint
+                && dir.join(&i.audio).exists()
+                && dir.join(&i.captions).exists()
+        });
+        if resumed {
+            state.done += chapter
+                .blocks
+                .iter()
+                .map(|i| chunk_sentences(i.text()).len())
+                .sum::<usize>();
+            progress(&state);
+            continue;
+        }
+        entries.truncate(index);
+
+        let mut audio = Vec::new();
+        let mut captions = Vec::new();
+        for block in &chapter.blocks {
+            let pause = match block {
+                Block::Heading(_) => options.heading_pause,
+                Block::Paragraph(_) => options.paragraph_pause,
+            };
+            let sentences = chunk_sentences(block.text());
+            let count = sentences.len();
+            for (i, sentence) in sentences.into_iter().enumerate() {
+                let synthesized = tts.synthesize(&sentence, voice).await?;
+                let time = |i: usize| from_samples(audio.len() + i);
+                captions.push(Cue {
+                    start: time(synthesized.speech.start),
+                    end: time(synthesized.speech.end),
+                    text: sentence,
+                });
+                audio.extend(synthesized.audio);
+                let pause = if i + 1 < count {
+                    options.sentence_pause
+                } else {
+                    pause
+                };
+                audio.resize(audio.len() + to_samples(pause), 0.);
+                state.done += 1;
+                progress(&state);
+            }
+        }
+
+        let entry = ChapterEntry {
+            title: chapter.title.clone(),
+            audio: format!("{}.wav", name).into(),
+            captions: format!("{}.srt", name).into(),
+            duration: from_samples(audio.len()),
+            fingerprint,
+        };
+        write_atomic(&dir.join(&entry.audio), &to_wav(&audio, SAMPLE_RATE))?;
+        write_atomic(&dir.join(&entry.captions), to_srt(&captions).as_bytes())?;
+        entries.push(entry);
+
+        // 每完成一章更新索引，作为中断后恢复的检查点
+        let index = json!({
+            "title": book.title,
+            "voice": voice.name(),
+            "speed": voice.speed(),
+            "chapters": entries.iter().map(ChapterEntry::to_json).collect::<Vec<_>>(),
+        });
+        write_atomic(&dir.join(INDEX_FILE), index.to_string().as_bytes())?;
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_text() {
+        let book = Book::from_text(
+            "序言\n很久以前。\n第一章 山\n有一座山。\n山上有座庙。\n第二章 庙\n庙里有个和尚。",
+        );
+        assert_eq!(
+            vec!["序言", "第一章 山", "第二章 庙"],
+            book.chapters
+                .iter()
+                .map(|i| i.title.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(3, book.chapters[1].blocks.len());
+
+        let book = Book::from_text("Once upon a time\nthere was a hill.\n\nThe end.");
+        assert_eq!(
+            vec![
+                Block::Paragraph("Once upon a time there was a hill.".to_owned()),
+                Block::Paragraph("The end.".to_owned())
+            ],
+            book.chapters[0].blocks
+        );
+    }
+
+    #[test]
+    fn test_from_markdown() {
+        let book = Book::from_markdown(
+            "# 书名\n\n前言。\n\n## 第一章\n\n这是**加粗**和[链接](http://a.b)。\n\n```\ncode\n```\n\n### 小节\n\n- 列表一\n- 列表二\n\n## 第二章\n\n结束。",
+        );
+        assert_eq!("书名", book.title);
+        assert_eq!(
+            vec!["", "第一章", "第二章"],
+            book.chapters
+                .iter()
+                .map(|i| i.title.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                Block::Heading("第一章".to_owned()),
+                Block::Paragraph("这是加粗和链接。".to_owned()),
+                Block::Heading("小节".to_owned()),
+                Block::Paragraph("列表一".to_owned()),
+                Block::Paragraph("列表二".to_owned()),
+            ],
+            book.chapters[1].blocks
+        );
+    }
+
+    #[test]
+    fn test_chapter_fingerprint() {
+        let chapter = |title: &str, text: &str| Chapter {
+            title: title.to_owned(),
+            blocks: vec![Block::Paragraph(text.to_owned())],
+        };
+        let options = AudiobookOptions::default();
+        let fingerprint =
+            |base, pauses, chapter: &Chapter| chapter_fingerprint(base, pauses, chapter, &options);
+        let a = fingerprint(1, None, &chapter("", "很久以前。"));
+        assert_eq!(a, fingerprint(1, None, &chapter("", "很久以前。")));
+        // 没有标题的章节修改了内容
+        assert_ne!(a, fingerprint(1, None, &chapter("", "很久很久以前。")));
+        // 换用其他的模型或设置
+        assert_ne!(a, fingerprint(2, None, &chapter("", "很久以前。")));
+        assert_ne!(
+            a,
+            fingerprint(1, Some(&Pauses::default()), &chapter("", "很久以前。"))
+        );
+        let slower = AudiobookOptions {
+            sentence_pause: Duration::from_millis(200),
+            ..Default::default()
+        };
+        assert_ne!(
+            a,
+            chapter_fingerprint(1, None, &chapter("", "很久以前。"), &slower)
+        );
+
+        let entry = ChapterEntry {
+            title: String::new(),
+            audio: "001.wav".into(),
+            captions: "001.srt".into(),
+            duration: Duration::from_secs(1),
+            fingerprint: a,
+        };
+        assert_eq!(
+            Some(entry.clone()),
+            ChapterEntry::from_json(&entry.to_json())
+        );
+    }
+
+    #[test]
+    fn test_chunk_sentences() {
+        let long = "很长的句子，".repeat(40);
+        let chunks = chunk_sentences(&format!("你好。{}", long));
+        assert_eq!("你好。", chunks[0]);
+        assert!(chunks.len() > 2);
+        assert!(chunks.iter().all(|i| i.chars().count() <= MAX_CHUNK_CHARS));
+        assert_eq!(long.trim(), chunks[1..].concat());
+    }
+}
@@ -0,0 +1,316 @@
+use {
+    super::{Book, join_line},
+    crate::KokoroError,
+    miniz_oxide::inflate::decompress_to_vec,
+    regex::Regex,
+    std::{collections::HashMap, mem::take, sync::LazyLock},
+};
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9:]*)([^>]*?)(/?)>|<!--.*?-->|<![^>]*>|<\?[^>]*\?>")
+        .unwrap()
+});
+static ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([a-zA-Z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+static ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").unwrap());
+
+fn invalid(msg: &str) -> KokoroError {
+    KokoroError::DocumentInvalid(format!("EPUB: {}", msg))
+}
+
+/// 只读的ZIP文件，只支持EPUB使用的存储和deflate压缩方式
+struct Archive<'a> {
+    data: &'a [u8],
+    // 文件名 -> (压缩方式, 压缩后的大小, 本地文件头的位置)
+    entries: HashMap<String, (u16, usize, usize)>,
+}
+
+impl<'a> Archive<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, KokoroError> {
+        let u16_at = |i: usize| {
+            data.get(i..i + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        };
+        let u32_at = |i: usize| {
+            data.get(i..i + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        };
+
+        // 中央目录结束记录在文件末尾，之后最多有65535字节的注释
+        let end = (0..data.len().saturating_sub(21))
+            .rev()
+            .take(65535 + 22)
+            .find(|i| u32_at(*i) == Some(0x06054b50))
+            .ok_or_else(|| invalid("not a zip file"))?;
+        let count = u16_at(end + 10).unwrap_or_default();
+        let mut pos = u32_at(end + 16).unwrap_or_default();
+
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            if u32_at(pos) != Some(0x02014b50) {
+                return Err(invalid("broken central directory"));
+            }
+            let field = |offset| u16_at(pos + offset).ok_or_else(|| invalid("truncated"));
+            let (method, name_len, extra_len, comment_len) =
+                (field(10)?, field(28)?, field(30)?, field(32)?);
+            let size = u32_at(pos + 20).ok_or_else(|| invalid("truncated"))?;
+            let offset = u32_at(pos + 42).ok_or_else(|| invalid("truncated"))?;
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(|| invalid("truncated"))?;
+            entries.insert(
+                String::from_utf8_lossy(name).into_owned(),
+                (method as u16, size, offset),
+            );
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, KokoroError> {
+        let &(method, size, offset) = self
+            .entries
+            .get(name)
+            .ok_or_else(|| invalid(&format!("missing {}", name)))?;
+        let header = self
+            .data
+            .get(offset..offset + 30)
+            .ok_or_else(|| invalid("truncated"))?;
+        let start = offset
+            + 30
+            + u16::from_le_bytes([header[26], header[27]]) as usize
+            + u16::from_le_bytes([header[28], header[29]]) as usize;
+        let data = self
+            .data
+            .get(start..start + size)
+            .ok_or_else(|| invalid("truncated"))?;
+        match method {
+            0 => Ok(data.to_vec()),
+            8 => decompress_to_vec(data).map_err(|e| invalid(&e.to_string())),
+            m => Err(invalid(&format!("unsupported compression method {}", m))),
+        }
+    }
+
+    fn read_string(&self, name: &str) -> Result<String, KokoroError> {
+        Ok(String::from_utf8_lossy(&self.read(name)?).into_owned())
+    }
+}
+
+fn attributes(tag: &str) -> HashMap<&str, &str> {
+    ATTRIBUTE
+        .captures_iter(tag)
+        .filter_map(|c| {
+            let value = c.get(2).or_else(|| c.get(3))?;
+            Some((c.get(1)?.as_str(), value.as_str()))
+        })
+        .collect()
+}
+
+fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |c: &regex::Captures| {
+            let entity = &c[1];
+            let code = if let Some(hex) = entity.strip_prefix("#x") {
+                u32::from_str_radix(hex, 16).ok()
+            } else if let Some(dec) = entity.strip_prefix('#') {
+                dec.parse().ok()
+            } else {
+                None
+            };
+            let decoded = match (code, entity) {
+                (Some(code), _) => char::from_u32(code),
+                (_, "amp") => Some('&'),
+                (_, "lt") => Some('<'),
+                (_, "gt") => Some('>'),
+                (_, "quot") => Some('"'),
+                (_, "apos") => Some('\''),
+                (_, "nbsp") => Some(' '),
+                _ => None,
+            };
+            decoded.map(String::from).unwrap_or_else(|| c[0].to_owned())
+        })
+        .into_owned()
+}
+
+/// 把相对于`base`所在目录的路径转换为压缩包中的路径
+fn resolve(base: &str, href: &str) -> String {
+    let href = href
+        .split('#')
+        .next()
+        .unwrap_or_default()
+        .replace("%20", " ");
+    let mut parts = base.split('/').collect::<Vec<_>>();
+    parts.pop();
+    for part in href.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            p => parts.push(p),
+        }
+    }
+    parts.join("/")
+}
+
+/// 取出累积的文本，合并其中的空白：行内标签之间的文本直接相连，只有原文中的空白才会变成空格
+fn take_text(text: &mut String) -> String {
+    let mut ret = String::new();
+    for word in take(text).split_whitespace() {
+        join_line(&mut ret, word);
+    }
+    ret
+}
+
+/// 把一个XHTML文档中的标题和段落加入书中
+fn add_document(book: &mut Book, html: &str) {
+    let mut text = String::new();
+    let mut heading = None;
+    // 在这些标签中的文本不朗读
+    let mut skip = 0usize;
+    let mut last = 0;
+    for tag in TAG.captures_iter(html) {
+        let Some(all) = tag.get(0) else {
+            continue;
+        };
+        if skip == 0 {
+            text.push_str(&decode_entities(&html[last..all.start()]));
+        }
+        last = all.end();
+        let Some(name) = tag.get(2) else {
+            continue;
+        };
+
+        let closing = !tag[1].is_empty();
+        let self_closing = !tag[4].is_empty();
+        let name = name.as_str().to_lowercase();
+        match name.as_str() {
+            "head" | "script" | "style" | "rt" | "rp" if !self_closing => {
+                skip = if closing {
+                    skip.saturating_sub(1)
+                } else {
+                    skip + 1
+                };
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if closing {
+                    let level = heading.take().unwrap_or(1);
+                    book.heading(take_text(&mut text), level <= 2);
+                } else {
+                    book.paragraph(take_text(&mut text));
+                    heading = name[1..].parse().ok();
+                }
+            }
+            "p" | "div" | "li" | "blockquote" | "section" | "tr" | "dt" | "dd" | "br" | "hr"
+                if heading.is_none() =>
+            {
+                book.paragraph(take_text(&mut text));
+            }
+            _ => {}
+        }
+    }
+    if skip == 0 {
+        text.push_str(&decode_entities(&html[last..]));
+    }
+    book.paragraph(take_text(&mut text));
+}
+
+impl Book {
+    /// 解析EPUB电子书
+    ///
+    /// 按书脊的顺序读取每个文档，每个文档和其中的一、二级标题开始新的章节，三级及以下的标题作为章节中的标题。
+    pub fn from_epub(data: &[u8]) -> Result<Self, KokoroError> {
+        let archive = Archive::new(data)?;
+        let container = archive.read_string("META-INF/container.xml")?;
+        let opf_path = TAG
+            .captures_iter(&container)
+            .filter(|i| i.get(2).is_some_and(|n| n.as_str() == "rootfile"))
+            .find_map(|i| attributes(&i[3]).get("full-path").map(|p| p.to_string()))
+            .ok_or_else(|| invalid("missing rootfile"))?;
+        let opf = archive.read_string(&opf_path)?;
+
+        let mut book = Self::default();
+        let mut manifest = HashMap::new();
+        let mut spine = Vec::new();
+        let mut title = None;
+        for tag in TAG.captures_iter(&opf) {
+            let Some(name) = tag.get(2) else {
+                continue;
+            };
+            let attributes = attributes(&tag[3]);
+            match name.as_str() {
+                "item" => {
+                    if let (Some(id), Some(href)) = (attributes.get("id"), attributes.get("href")) {
+                        manifest.insert(id.to_string(), resolve(&opf_path, href));
+                    }
+                }
+                "itemref" if attributes.get("linear") != Some(&"no") => {
+                    if let Some(id) = attributes.get("idref") {
+                        spine.push(id.to_string());
+                    }
+                }
+                "dc:title" if tag[1].is_empty() && title.is_none() => {
+                    let start = tag.get(0).map_or(0, |i| i.end());
+                    let end = opf[start..].find("</").map_or(start, |i| start + i);
+                    title = Some(decode_entities(opf[start..end].trim()));
+                }
+                _ => {}
+            }
+        }
+        book.title = title.unwrap_or_default();
+
+        for id in spine {
+            let Some(path) = manifest.get(&id) else {
+                continue;
+            };
+            book.start_chapter();
+            add_document(&mut book, &archive.read_string(path)?);
+        }
+        Ok(book.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_document() {
+        let mut book = Book::default();
+        add_document(
+            &mut book,
+            "<?xml version=\"1.0\"?><html><head><title>忽略</title></head><body>\
+             <h1 class=\"c\">第一章</h1><p>你好，<b>世界</b>&#12290;</p><p>Tom &amp;<br/>Jerry</p>\
+             <h3>小节</h3><div>结束<ruby>了<rt>le</rt></ruby>。</div>\
+             <p>“<span class=\"dropcap\">O</span>nce,” he said,\n  “un<i>believ</i>able\n<b>world</b>!”</p>\
+             </body></html>",
+        );
+        add_document(&mut book, "<body><h2>第二章</h2><p>再见。</p></body>");
+        let book = book.finish();
+        assert_eq!(
+            vec!["第一章", "第二章"],
+            book.chapters
+                .iter()
+                .map(|i| i.title.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                "第一章",
+                "你好，世界。",
+                "Tom &",
+                "Jerry",
+                "小节",
+                "结束了。",
+                "“Once,” he said, “unbelievable world!”"
+            ],
+            book.chapters[0]
+                .blocks
+                .iter()
+                .map(|i| i.text())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("a/c/d.html", resolve("a/b/content.opf", "../c/./d.html#x"));
+    }
+}
@@ -0,0 +1,49 @@
+use {
+    clap::Parser,
+    kokoro_tts::{KokoroTts, SynthCache, openai_router, serve_wyoming},
+    std::{error::Error, net::SocketAddr, path::PathBuf, sync::Arc},
+    tokio::net::TcpListener,
+};
+
+/// 兼容OpenAI语音合成接口的HTTP服务
+#[derive(Parser)]
+#[command(name = "kokoro-server", version, about)]
+struct Args {
+    /// 模型文件
+    #[arg(short, long, default_value = "kokoro-v1.1-zh.onnx")]
+    model: PathBuf,
+    /// 发音人文件
+    #[arg(long, default_value = "voices-v1.1-zh.bin")]
+    voices: PathBuf,
+    /// 监听地址
+    #[arg(short, long, default_value = "127.0.0.1:8880")]
+    listen: SocketAddr,
+    /// 同时在该地址上提供Wyoming协议的服务，供Home Assistant等客户端使用
+    #[arg(long)]
+    wyoming: Option<SocketAddr>,
+    /// 启用缓存，指定内存中缓存的字节数上限
+    #[arg(long)]
+    cache_size: Option<usize>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let mut tts = KokoroTts::new(&args.model, &args.voices).await?;
+    if let Some(size) = args.cache_size {
+        tts = tts.with_cache(Arc::new(SynthCache::new(size)));
+    }
+    let tts = Arc::new(tts);
+    if let Some(addr) = args.wyoming {
+        let listener = TcpListener::bind(addr).await?;
+        println!("Wyoming listening on tcp://{}", addr);
+        tokio::spawn(serve_wyoming(tts.clone(), listener));
+    }
+    let app = openai_router(tts);
+
+    let listener = TcpListener::bind(args.listen).await?;
+    println!("Listening on http://{}", args.listen);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
@@ -0,0 +1,389 @@
+use {
+    clap::{Parser, ValueEnum},
+    futures::executor::block_on,
+    kokoro_tts::{
+        AudiobookOptions, Book, Cue, DialogueOptions, G2p, KokoroTts, Pauses, SAMPLE_RATE, Speaker,
+        Voice, parse_script, parse_subtitles, resample, to_pcm_s16le, to_srt, to_vtt, to_wav,
+    },
+    std::{
+        collections::HashMap,
+        error::Error,
+        fs::{create_dir_all, read_to_string, write},
+        io::{Read, Write, stdin, stdout},
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+};
+
+/// Kokoro离线语音合成命令行工具
+#[derive(Parser)]
+#[command(name = "kokoro", version, about)]
+struct Args {
+    /// 要合成的文本，不指定时从--file或标准输入读取
+    text: Vec<String>,
+    /// 从文件读取文本，"-"表示标准输入
+    #[arg(short, long, conflicts_with = "text")]
+    file: Option<PathBuf>,
+    /// 批量模式，文件中的每一行作为一段单独的语音，依次输出到--output指定的目录
+    #[arg(short, long, conflicts_with_all = ["text", "file"])]
+    batch: Option<PathBuf>,
+    /// 根据SRT或WebVTT字幕配音，每条字幕放在它的开始时间处，输出一条连续的音轨
+    #[arg(long, conflicts_with_all = ["text", "file", "batch"])]
+    subtitles: Option<PathBuf>,
+    /// 配音时最大的加速倍数，仍然放不下的字幕会输出到标准错误
+    #[arg(long, default_value_t = 1.5, requires = "subtitles")]
+    max_rate: f32,
+    /// 有声书模式，读取纯文本、Markdown或EPUB文件，每章一个WAV文件和SRT字幕，输出到--output指定的目录，中断后再次运行会跳过已完成的章节
+    #[arg(long, conflicts_with_all = ["text", "file", "batch", "subtitles"])]
+    audiobook: Option<PathBuf>,
+    /// 有声书中段落之后停顿的秒数
+    #[arg(long, default_value = "0.6", value_parser = parse_seconds, requires = "audiobook")]
+    paragraph_pause: Duration,
+    /// 有声书中标题之后停顿的秒数
+    #[arg(long, default_value = "1", value_parser = parse_seconds, requires = "audiobook")]
+    heading_pause: Duration,
+    /// 根据对话脚本合成一条音轨，每行为"说话人: 台词"，说话人的发音人由--cast指定
+    #[arg(long, conflicts_with_all = ["text", "file", "batch", "subtitles", "audiobook"])]
+    script: Option<PathBuf>,
+    /// 说话人使用的发音人，格式为"说话人=发音人[:语速[:位置]]"，位置从-1（左）到1（右），可以指定多次
+    #[arg(long, requires = "script")]
+    cast: Vec<String>,
+    /// 对话中台词之间停顿的秒数
+    #[arg(long, default_value = "0.4", value_parser = parse_seconds, requires = "script")]
+    turn_gap: Duration,
+    /// 对话输出立体声，按照--cast中的位置放置说话人
+    #[arg(long, requires = "script")]
+    stereo: bool,
+    /// 模型文件
+    #[arg(short, long, default_value = "kokoro-v1.1-zh.onnx")]
+    model: PathBuf,
+    /// 发音人文件
+    #[arg(long, default_value = "voices-v1.1-zh.bin")]
+    voices: PathBuf,
+    /// 发音人名称
+    #[arg(short, long, default_value = "zm_045")]
+    voice: String,
+    /// 语速
+    #[arg(short, long, default_value_t = 1.)]
+    speed: f32,
+    /// 在标点和换行处插入固定的停顿，依次为逗号、分号冒号、句末、换行和空行的毫秒数，不指定数值时使用默认值
+    #[arg(long, num_args = 0..=1, default_missing_value = "200,300,400,500,900")]
+    pauses: Option<String>,
+    /// 输出格式
+    #[arg(long, value_enum, default_value_t = Format::Wav)]
+    format: Format,
+    /// 输出采样率
+    #[arg(short = 'r', long, default_value_t = SAMPLE_RATE)]
+    sample_rate: u32,
+    /// 输出文件，不指定或为"-"时写入标准输出；批量模式下为输出目录
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// 同时输出按句子对齐的字幕，扩展名为.vtt时输出WebVTT，否则输出SRT
+    #[arg(long, conflicts_with_all = ["batch", "subtitles", "audiobook"])]
+    captions: Option<PathBuf>,
+    /// 列出发音人文件中的所有发音人
+    #[arg(long)]
+    list_voices: bool,
+    /// 只输出g2p转换后的音素
+    #[arg(long)]
+    phonemes_only: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// 16位单声道WAV文件
+    Wav,
+    /// 16位有符号小端序的原始PCM数据
+    Pcm,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Pcm => "pcm",
+        }
+    }
+
+    fn encode(&self, samples: &[f32], sample_rate: u32) -> Vec<u8> {
+        match self {
+            Self::Wav => to_wav(samples, sample_rate),
+            Self::Pcm => to_pcm_s16le(samples),
+        }
+    }
+}
+
+fn read_input(path: Option<&Path>) -> std::io::Result<String> {
+    match path {
+        Some(path) if path != Path::new("-") => read_to_string(path),
+        _ => {
+            let mut text = String::new();
+            stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn write_output(path: Option<&Path>, data: &[u8]) -> std::io::Result<()> {
+    match path {
+        Some(path) if path != Path::new("-") => write(path, data),
+        _ => {
+            let mut out = stdout().lock();
+            out.write_all(data)?;
+            out.flush()
+        }
+    }
+}
+
+fn write_captions(path: &Path, captions: &[Cue]) -> std::io::Result<()> {
+    let captions = match path.extension().and_then(|i| i.to_str()) {
+        Some("vtt") => to_vtt(captions),
+        _ => to_srt(captions),
+    };
+    write(path, captions)
+}
+
+/// 解析"说话人=发音人[:语速[:位置]]"，没有指定语速时使用`speed`
+fn parse_cast(cast: &str, speed: f32) -> Result<(String, Speaker), Box<dyn Error>> {
+    let (name, spec) = cast
+        .split_once('=')
+        .ok_or_else(|| format!("invalid cast: {}", cast))?;
+    let mut parts = spec.split(':');
+    let voice = parts.next().unwrap_or_default();
+    let speed = parts.next().map(str::parse).transpose()?.unwrap_or(speed);
+    let pan = parts.next().map(str::parse).transpose()?.unwrap_or(0.);
+    let speaker = Speaker {
+        voice: Voice::from_name(voice, speed)?,
+        pan,
+    };
+    Ok((name.trim().to_owned(), speaker))
+}
+
+/// 解析秒数，负数、无穷大等无法表示为时长的值会报错
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    let seconds = seconds.parse::<f32>().map_err(|e| e.to_string())?;
+    Duration::try_from_secs_f32(seconds).map_err(|e| e.to_string())
+}
+
+/// 解析逗号分隔的五个毫秒数
+fn parse_pauses(pauses: &str) -> Result<Pauses, Box<dyn Error>> {
+    let ms = pauses
+        .split(',')
+        .map(|i| i.trim().parse().map(Duration::from_millis))
+        .collect::<Result<Vec<_>, _>>()?;
+    let [comma, clause, sentence, line_break, paragraph] = ms[..] else {
+        return Err(format!("expected 5 pauses: {}", pauses).into());
+    };
+    Ok(Pauses {
+        comma,
+        clause,
+        sentence,
+        line_break,
+        paragraph,
+    })
+}
+
+fn load(args: &Args) -> Result<KokoroTts, Box<dyn Error>> {
+    // 加载模型不依赖异步运行时，直接在当前线程中完成
+    let tts = block_on(KokoroTts::new(&args.model, &args.voices))?;
+    Ok(match &args.pauses {
+        Some(pauses) => tts.with_pauses(parse_pauses(pauses)?),
+        None => tts,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    if args.list_voices {
+        let tts = load(&args)?;
+        let mut names = tts.voice_names().collect::<Vec<_>>();
+        names.sort_unstable();
+        for name in names {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+
+    let voice = Voice::from_name(&args.voice, args.speed)?;
+    if let Some(subtitles) = &args.subtitles {
+        let cues = parse_subtitles(&read_input(Some(subtitles))?)?;
+        let tts = load(&args)?;
+        let dubbing = tts.dub(&cues, voice, args.max_rate).await?;
+        for i in &dubbing.overflows {
+            eprintln!(
+                "#{} overflowed by {:.2}s: {}",
+                i.index + 1,
+                i.overflow.as_secs_f32(),
+                i.text
+            );
+        }
+        let audio = resample(&dubbing.audio, SAMPLE_RATE, args.sample_rate);
+        write_output(
+            args.output.as_deref(),
+            &args.format.encode(&audio, args.sample_rate),
+        )?;
+        return Ok(());
+    }
+    if let Some(path) = &args.audiobook {
+        let book = Book::from_file(path)?;
+        let options = AudiobookOptions {
+            paragraph_pause: args.paragraph_pause,
+            heading_pause: args.heading_pause,
+            ..Default::default()
+        };
+        let tts = load(&args)?;
+        let dir = args.output.unwrap_or_else(|| PathBuf::from("."));
+        let chapters = tts
+            .synth_audiobook(&book, voice, &options, &dir, |p| {
+                eprint!(
+                    "\rchapter {}/{}, sentence {}/{}",
+                    p.chapter + 1,
+                    p.chapters,
+                    p.done,
+                    p.total
+                );
+            })
+            .await?;
+        eprintln!();
+        for i in chapters {
+            eprintln!("{} {}", dir.join(&i.audio).display(), i.title);
+        }
+        return Ok(());
+    }
+    if let Some(script) = &args.script {
+        let lines = parse_script(&read_input(Some(script))?)?;
+        let cast = args
+            .cast
+            .iter()
+            .map(|i| parse_cast(i, args.speed))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        let options = DialogueOptions {
+            turn_gap: args.turn_gap,
+            stereo: args.stereo,
+        };
+        let tts = load(&args)?;
+        let dialogue = tts.synth_dialogue(&lines, &cast, &options).await?;
+        if let Some(path) = &args.captions {
+            write_captions(path, &dialogue.captions)?;
+        }
+        let data = match args.format {
+            Format::Wav => dialogue.to_wav(args.sample_rate),
+            Format::Pcm => {
+                let channels = dialogue
+                    .channels
+                    .iter()
+                    .map(|i| resample(i, SAMPLE_RATE, args.sample_rate))
+                    .collect::<Vec<_>>();
+                // 多声道的PCM数据按采样交错排列
+                let len = channels.first().map_or(0, Vec::len);
+                let samples = (0..len)
+                    .flat_map(|i| channels.iter().map(move |c| c[i]))
+                    .collect::<Vec<_>>();
+                to_pcm_s16le(&samples)
+            }
+        };
+        write_output(args.output.as_deref(), &data)?;
+        return Ok(());
+    }
+    let texts = match &args.batch {
+        Some(batch) => read_input(Some(batch))?
+            .lines()
+            .map(str::trim)
+            .filter(|i| !i.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+        None if !args.text.is_empty() => vec![args.text.join(" ")],
+        None => vec![read_input(args.file.as_deref())?],
+    };
+
+    if args.phonemes_only {
+        let g2p = G2p::new()?;
+        for text in texts {
+            let phonemes = g2p.g2p(&text, voice.is_v11_supported(), voice.english_variant())?;
+            println!("{}", phonemes);
+        }
+        return Ok(());
+    }
+
+    let tts = load(&args)?;
+    if args.batch.is_some() {
+        let dir = args.output.unwrap_or_else(|| PathBuf::from("."));
+        create_dir_all(&dir)?;
+        for (i, text) in texts.iter().enumerate() {
+            let (audio, _) = tts.synth(text, voice).await?;
+            let audio = resample(&audio, SAMPLE_RATE, args.sample_rate);
+            let path = dir.join(format!("{:04}.{}", i + 1, args.format.extension()));
+            write(&path, args.format.encode(&audio, args.sample_rate))?;
+            eprintln!("{}", path.display());
+        }
+    } else {
+        let audio = match &args.captions {
+            Some(path) => {
+                let (audio, captions) = tts.synth_with_captions(&texts[0], voice).await?;
+                write_captions(path, &captions)?;
+                audio
+            }
+            None => tts.synth(&texts[0], voice).await?.0,
+        };
+        let audio = resample(&audio, SAMPLE_RATE, args.sample_rate);
+        write_output(
+            args.output.as_deref(),
+            &args.format.encode(&audio, args.sample_rate),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cast() -> Result<(), Box<dyn Error>> {
+        let (name, speaker) = parse_cast("旁白=zf_xiaoxiao", 1.2)?;
+        assert_eq!("旁白", name);
+        assert_eq!("zf_xiaoxiao", speaker.voice.name());
+        assert_eq!(1.2, speaker.voice.speed());
+        assert_eq!(0., speaker.pan);
+
+        let (name, speaker) = parse_cast(" Bob =am_adam:0.9:-0.5", 1.)?;
+        assert_eq!("Bob", name);
+        assert_eq!("am_adam", speaker.voice.name());
+        assert_eq!(0.9, speaker.voice.speed());
+        assert_eq!(-0.5, speaker.pan);
+
+        assert!(parse_cast("zf_xiaoxiao", 1.).is_err());
+        assert!(parse_cast("A=zf_nobody", 1.).is_err());
+        assert!(parse_cast("A=zf_xiaoxiao:fast", 1.).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(Ok(Duration::from_millis(500)), parse_seconds("0.5"));
+        assert_eq!(Ok(Duration::ZERO), parse_seconds("0"));
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert!(parse_seconds("abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_pauses() -> Result<(), Box<dyn Error>> {
+        let pauses = parse_pauses("100, 200,300,400,500")?;
+        assert_eq!(Duration::from_millis(100), pauses.comma);
+        assert_eq!(Duration::from_millis(200), pauses.clause);
+        assert_eq!(Duration::from_millis(300), pauses.sentence);
+        assert_eq!(Duration::from_millis(400), pauses.line_break);
+        assert_eq!(Duration::from_millis(500), pauses.paragraph);
+
+        assert!(parse_pauses("100,200,300,400").is_err());
+        assert!(parse_pauses("100,200,300,400,500,600").is_err());
+        assert!(parse_pauses("100,200,-300,400,500").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,529 @@
+use {
+    futures::{StreamExt, stream::FuturesOrdered},
+    kokoro_tts::{
+        KokoroError, KokoroTts, SAMPLE_RATE, SynthSink, Voice, resample, split_sentences,
+        time_stretch,
+    },
+    rodio::{
+        OutputStreamBuilder, Sink, buffer::SamplesBuffer, mixer::Mixer, source::EmptyCallback,
+    },
+    std::{
+        collections::VecDeque,
+        env::args,
+        error::Error,
+        fs::read_to_string,
+        io::{Write, stdout},
+        path::PathBuf,
+    },
+    tokio::{
+        io::{AsyncBufReadExt, BufReader, Lines, Stdin, stdin},
+        select,
+        sync::mpsc::{UnboundedSender, unbounded_channel},
+        task::{JoinHandle, spawn_blocking},
+    },
+};
+
+type Input = Lines<BufReader<Stdin>>;
+
+/// 模块配置，从speech-dispatcher传入的配置文件中读取
+struct Config {
+    model: PathBuf,
+    voices: PathBuf,
+    default_voice: Option<String>,
+}
+
+impl Config {
+    /// 解析`KokoroModel "..."`形式的配置项，配置文件不存在时使用默认值
+    fn load(path: Option<String>) -> Self {
+        let mut config = Self {
+            model: PathBuf::from("kokoro-v1.1-zh.onnx"),
+            voices: PathBuf::from("voices-v1.1-zh.bin"),
+            default_voice: None,
+        };
+        let text = path
+            .and_then(|p| read_to_string(p).ok())
+            .unwrap_or_default();
+        for line in text.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"');
+            match key {
+                "KokoroModel" => config.model = PathBuf::from(value),
+                "KokoroVoices" => config.voices = PathBuf::from(value),
+                "KokoroDefaultVoice" => config.default_voice = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+/// 待合成文本中的片段，SSML中的`<mark>`标签对应索引标记
+enum Segment {
+    Text(String),
+    Mark(String),
+}
+
+/// 播放到某个位置时由音频线程发回的事件
+#[derive(Clone)]
+enum Playback {
+    Mark(String),
+    End,
+}
+
+/// 播放队列，顺序与合成请求一致
+enum Item {
+    Audio,
+    Event(Playback),
+}
+
+fn reply(text: &str) {
+    let mut out = stdout().lock();
+    let _ = writeln!(out, "{}", text);
+    let _ = out.flush();
+}
+
+/// 读取命令之后以单独一行"."结尾的数据，".."开头的行表示以"."开头
+async fn read_block(lines: &mut Input) -> std::io::Result<Vec<String>> {
+    let mut block = Vec::new();
+    while let Some(line) = lines.next_line().await? {
+        match line.as_str() {
+            "." => break,
+            l if l.starts_with("..") => block.push(l[1..].to_owned()),
+            _ => block.push(line),
+        }
+    }
+    Ok(block)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 去掉SSML标签，保留文本和索引标记
+fn parse_ssml(ssml: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = ssml;
+    while let Some(start) = rest.find('<') {
+        segments.push(Segment::Text(unescape(&rest[..start])));
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        if let Some(attrs) = tag.strip_prefix("mark")
+            && let Some(name) = attrs.split('"').nth(1)
+        {
+            segments.push(Segment::Mark(unescape(name)));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    segments.push(Segment::Text(unescape(rest)));
+    segments
+}
+
+/// 调整音高而保持时长，`pitch`的范围为-100到100，两端分别降低和升高半个八度
+///
+/// 模型无法调整音高，先重新采样改变音高和时长，再通过时间拉伸恢复时长。
+fn shift_pitch(audio: &[f32], pitch: i32) -> Vec<f32> {
+    if pitch == 0 {
+        return audio.to_vec();
+    }
+    let factor = 2f32.powf(pitch.clamp(-100, 100) as f32 / 200.);
+    let shifted = resample(audio, (SAMPLE_RATE as f32 * factor) as u32, SAMPLE_RATE);
+    time_stretch(&shifted, 1. / factor)
+}
+
+fn language_matches(voice: &str, language: &str) -> bool {
+    let language = language.to_ascii_lowercase().replace('_', "-");
+    voice == language || voice.split('-').next() == language.split('-').next()
+}
+
+struct Module {
+    voices: Vec<String>,
+    default_voice: Option<String>,
+    sink: SynthSink<String>,
+    mixer: Mixer,
+    player: Option<Sink>,
+    queue: VecDeque<Item>,
+    // 正在后台线程中调整音高的音频，按合成的顺序放入播放器
+    shifting: FuturesOrdered<JoinHandle<Vec<f32>>>,
+    events: UnboundedSender<(u64, Playback)>,
+    job: u64,
+    speaking: bool,
+    pausing: bool,
+    rate: i32,
+    pitch: i32,
+    volume: i32,
+    voice_type: String,
+    synthesis_voice: Option<String>,
+    language: Option<String>,
+}
+
+impl Module {
+    /// 按照当前设置选择发音人：依次使用指定的发音人、配置的默认发音人，或按声音类型（MALE1、FEMALE2等）选择
+    fn voice(&self) -> Result<Voice, KokoroError> {
+        let speed = 2f32.powf(self.rate as f32 / 100.);
+        if let Some(name) = &self.synthesis_voice
+            && self.voices.contains(name)
+        {
+            return Voice::from_name(name, speed);
+        }
+
+        let voices = self
+            .voices
+            .iter()
+            .filter_map(|name| Voice::from_name(name, speed).ok())
+            .collect::<Vec<_>>();
+        let mut candidates = voices
+            .iter()
+            .filter(|v| {
+                self.language
+                    .as_deref()
+                    .is_some_and(|l| language_matches(v.language(), l))
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            candidates = voices;
+        }
+        if let Some(voice) = candidates
+            .iter()
+            .find(|v| self.default_voice.as_deref() == Some(v.name()))
+        {
+            return Ok(*voice);
+        }
+
+        let female = self.voice_type.contains("FEMALE");
+        let same_gender = candidates
+            .iter()
+            .filter(|v| v.is_female() == female)
+            .collect::<Vec<_>>();
+        let index = self
+            .voice_type
+            .trim_start_matches(|c: char| !c.is_ascii_digit())
+            .parse::<usize>()
+            .unwrap_or(1)
+            .saturating_sub(1);
+        same_gender
+            .get(index % same_gender.len().max(1))
+            .copied()
+            .or(candidates.first())
+            .copied()
+            .ok_or_else(|| KokoroError::VoiceNotFound(self.voice_type.clone()))
+    }
+
+    fn set(&mut self, settings: Vec<String>) {
+        for setting in settings {
+            let Some((key, value)) = setting.split_once('=') else {
+                continue;
+            };
+            let value = (value != "NULL").then(|| value.to_owned());
+            match key {
+                "rate" => self.rate = value.and_then(|v| v.parse().ok()).unwrap_or_default(),
+                "pitch" => self.pitch = value.and_then(|v| v.parse().ok()).unwrap_or_default(),
+                "volume" => self.volume = value.and_then(|v| v.parse().ok()).unwrap_or(100),
+                "voice" | "voice_type" => self.voice_type = value.unwrap_or_default(),
+                "synthesis_voice" => self.synthesis_voice = value,
+                "language" => self.language = value,
+                // 音高范围等参数无法调整
+                _ => {}
+            }
+        }
+    }
+
+    fn list_voices(&self) {
+        let mut list = String::new();
+        for name in &self.voices {
+            let language = Voice::from_name(name, 1.)
+                .map(|v| v.language())
+                .unwrap_or_default();
+            list.push_str(&format!("200-{}\t{}\tnone\n", name, language));
+        }
+        list.push_str("200 OK VOICE LIST SENT");
+        reply(&list);
+    }
+
+    /// 立即停止合成和播放，之前的回调事件都会被忽略
+    fn interrupt(&mut self) {
+        self.sink.cancel();
+        self.queue.clear();
+        self.shifting = FuturesOrdered::new();
+        if let Some(player) = self.player.take() {
+            player.stop();
+        }
+        self.job += 1;
+        self.speaking = false;
+        self.pausing = false;
+    }
+
+    async fn speak(&mut self, segments: Vec<Segment>) -> Result<(), KokoroError> {
+        self.interrupt();
+        self.sink.set_voice(self.voice()?);
+        let player = Sink::connect_new(&self.mixer);
+        player.set_volume((self.volume + 100).max(0) as f32 / 200.);
+        self.player = Some(player);
+        self.speaking = true;
+        reply("701 BEGIN");
+
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => {
+                    for sentence in split_sentences(&text) {
+                        self.sink.synth(sentence).await?;
+                        self.queue.push_back(Item::Audio);
+                    }
+                }
+                Segment::Mark(name) => self.queue.push_back(Item::Event(Playback::Mark(name))),
+            }
+        }
+        self.queue.push_back(Item::Event(Playback::End));
+        Ok(())
+    }
+
+    fn waiting_audio(&self) -> bool {
+        matches!(self.queue.front(), Some(Item::Audio))
+    }
+
+    /// 把队首的索引标记和结束标记放入播放器，播放到这里时发回事件
+    ///
+    /// 之前的音频调整完音高并放入播放器之后才放入标记。
+    fn flush_marks(&mut self) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        if !self.shifting.is_empty() {
+            return;
+        }
+        while !self.waiting_audio()
+            && let Some(Item::Event(event)) = self.queue.pop_front()
+        {
+            let (events, job) = (self.events.clone(), self.job);
+            player.append(EmptyCallback::new(Box::new(move || {
+                let _ = events.send((job, event.clone()));
+            })));
+        }
+    }
+
+    /// 在后台线程中调整音高，调整较长的音频时也能及时响应STOP等命令
+    fn play(&mut self, audio: Vec<f32>) {
+        self.queue.pop_front();
+        if self.player.is_some() {
+            let pitch = self.pitch;
+            self.shifting
+                .push_back(spawn_blocking(move || shift_pitch(&audio, pitch)));
+        }
+    }
+
+    fn append(&mut self, audio: Vec<f32>) {
+        if let Some(player) = &self.player {
+            player.append(SamplesBuffer::new(1, SAMPLE_RATE, audio));
+        }
+    }
+
+    fn playback(&mut self, job: u64, event: Playback) {
+        if job != self.job {
+            return;
+        }
+        match event {
+            Playback::Mark(name) => {
+                reply(&format!("700-{}\n700 INDEX MARK", name));
+                // 暂停时在索引标记处停下，服务端之后会从该标记处继续
+                if self.pausing {
+                    self.interrupt();
+                    reply("704 PAUSED");
+                }
+            }
+            Playback::End => {
+                self.player = None;
+                self.speaking = false;
+                self.pausing = false;
+                reply("702 END");
+            }
+        }
+    }
+
+    /// 处理一条命令，返回`false`表示退出
+    async fn command(&mut self, line: &str, lines: &mut Input) -> Result<bool, Box<dyn Error>> {
+        match line {
+            "SPEAK" | "CHAR" | "KEY" | "SOUND_ICON" => {
+                reply("202 OK RECEIVING MESSAGE");
+                let data = read_block(lines).await?.join("\n");
+                let segments = match line {
+                    "SPEAK" => parse_ssml(&data),
+                    "CHAR" if data == "space" => vec![Segment::Text(" ".to_owned())],
+                    "CHAR" => vec![Segment::Text(data)],
+                    "KEY" => vec![Segment::Text(data.replace('_', " "))],
+                    // 没有声音图标可以播放
+                    _ => Vec::new(),
+                };
+                reply("200 OK SPEAKING");
+                if let Err(e) = self.speak(segments).await {
+                    eprintln!("Synthesis failed: {}", e);
+                    self.interrupt();
+                    reply("703 STOPPED");
+                }
+            }
+            "STOP" => {
+                if self.speaking {
+                    self.interrupt();
+                    reply("703 STOPPED");
+                }
+            }
+            "PAUSE" => self.pausing = self.speaking,
+            "SET" => {
+                reply("203 OK RECEIVING SETTINGS");
+                let settings = read_block(lines).await?;
+                self.set(settings);
+                reply("203 OK SETTINGS RECEIVED");
+            }
+            "AUDIO" => {
+                // 音频总是由本模块自己播放
+                reply("207 OK RECEIVING AUDIO SETTINGS");
+                read_block(lines).await?;
+                reply("203 OK AUDIO INITIALIZED");
+            }
+            "LOGLEVEL" => {
+                reply("207 OK RECEIVING LOGLEVEL SETTINGS");
+                read_block(lines).await?;
+                reply("203 OK LOGLEVEL SET");
+            }
+            "LIST VOICES" => self.list_voices(),
+            "QUIT" => {
+                self.interrupt();
+                reply("210 OK QUIT");
+                return Ok(false);
+            }
+            _ => reply("300 ERR UNKNOWN COMMAND"),
+        }
+        Ok(true)
+    }
+}
+
+/// speech-dispatcher的Kokoro输出模块
+///
+/// speech-dispatcher启动模块时把配置文件路径作为第一个参数传入，之后通过标准输入输出按照模块协议通信。
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::load(args().nth(1));
+    let mut lines = BufReader::new(stdin()).lines();
+
+    // 服务端启动模块后首先发送INIT
+    match lines.next_line().await?.as_deref() {
+        Some("INIT") => {}
+        _ => return Ok(()),
+    }
+    let init = async {
+        let tts = KokoroTts::new(&config.model, &config.voices).await?;
+        let output = OutputStreamBuilder::open_default_stream()?;
+        Ok::<_, Box<dyn Error>>((tts, output))
+    };
+    let (tts, output) = match init.await {
+        Ok(i) => i,
+        Err(e) => {
+            reply(&format!("399-{}\n399 ERR CANT INIT MODULE", e));
+            return Ok(());
+        }
+    };
+    reply("299-Kokoro initialized successfully.\n299 OK LOADED SUCCESSFULLY");
+
+    let mut voices = tts.voice_names().map(ToOwned::to_owned).collect::<Vec<_>>();
+    voices.sort_unstable();
+    let first = Voice::from_name(voices.first().map_or("", String::as_str), 1.)?;
+    let (sink, mut stream) = tts.stream(first);
+    let (events, mut playback) = unbounded_channel();
+    let mut module = Module {
+        voices,
+        default_voice: config.default_voice,
+        sink,
+        mixer: output.mixer().clone(),
+        player: None,
+        queue: VecDeque::new(),
+        shifting: FuturesOrdered::new(),
+        events,
+        job: 0,
+        speaking: false,
+        pausing: false,
+        rate: 0,
+        pitch: 0,
+        volume: 100,
+        voice_type: "MALE1".to_owned(),
+        synthesis_voice: None,
+        language: None,
+    };
+
+    loop {
+        module.flush_marks();
+        select! {
+            biased;
+            Some((job, event)) = playback.recv() => module.playback(job, event),
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    break;
+                };
+                if !module.command(line.trim_end(), &mut lines).await? {
+                    break;
+                }
+            }
+            Some(audio) = module.shifting.next() => match audio {
+                Ok(audio) => module.append(audio),
+                Err(e) => eprintln!("Pitch shifting failed: {}", e),
+            },
+            audio = stream.next(), if module.waiting_audio() => match audio {
+                Some((audio, _)) => {
+                    // 不使用字幕，取出后丢弃以免守护进程的内存持续增长
+                    stream.take_captions();
+                    module.play(audio)
+                }
+                None => {
+                    // 合成失败时流随之结束，换用新的合成会话
+                    if let Some(e) = stream.take_error() {
+                        eprintln!("Synthesis failed: {}", e);
+                    }
+                    (module.sink, stream) = tts.stream(first);
+                    module.interrupt();
+                    reply("703 STOPPED");
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift_pitch() {
+        let sine = (0..SAMPLE_RATE)
+            .map(|i| (2. * std::f32::consts::PI * 220. * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect::<Vec<_>>();
+        let crossings = |s: &[f32]| s.windows(2).filter(|w| w[0] < 0. && w[1] >= 0.).count();
+
+        assert_eq!(sine, shift_pitch(&sine, 0));
+        for (pitch, frequency) in [(100, 311.), (-100, 156.), (50, 262.)] {
+            let shifted = shift_pitch(&sine, pitch);
+            let seconds = shifted.len() as f32 / SAMPLE_RATE as f32;
+            assert!(
+                (seconds - 1.).abs() < 0.02,
+                "{} at pitch {}",
+                seconds,
+                pitch
+            );
+            let measured = crossings(&shifted) as f32 / seconds;
+            assert!(
+                (measured - frequency).abs() < 10.,
+                "{} at pitch {}",
+                measured,
+                pitch
+            );
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! 同步接口
+//!
+//! 不需要任何异步运行时，适用于桌面程序、FFI等同步的调用方。合成在调用方的线程中进行。
+
+use {
+    crate::{
+        AudiobookOptions, Book, ChapterEntry, Cue, Dialogue, DialogueOptions, Dubbing,
+        EnglishVariant, FittedAudio, KokoroError, Line, ModelVersion, Pauses, Progress, Speaker,
+        SynthCache, Vocabulary, Voice,
+    },
+    futures::{StreamExt, executor::block_on},
+    std::{collections::HashMap, path::Path, sync::Arc, time::Duration},
+};
+
+/// 同步的语音合成器
+///
+/// 与[`crate::KokoroTts`]功能相同，所有方法都会阻塞直到完成。
+pub struct KokoroTts {
+    inner: crate::KokoroTts,
+}
+
+impl KokoroTts {
+    /// 从模型文件和发音人文件创建合成器
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use kokoro_tts::{Voice, blocking::KokoroTts};
+    ///
+    /// let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin") else {
+    ///     return;
+    /// };
+    /// let _ = tts.synth("你好", Voice::Zf003(1.));
+    /// ```
+    ///
+    pub fn new<P: AsRef<Path>>(model_path: P, voices_path: P) -> Result<Self, KokoroError> {
+        block_on(crate::KokoroTts::new(model_path, voices_path)).map(Self::from)
+    }
+
+    /// 从内存中的模型和发音人数据创建合成器
+    pub fn new_from_bytes<B>(model: B, voices: B) -> Result<Self, KokoroError>
+    where
+        B: AsRef<[u8]>,
+    {
+        block_on(crate::KokoroTts::new_from_bytes(model, voices)).map(Self::from)
+    }
+
+    /// 获取加载的模型版本
+    pub fn model_version(&self) -> ModelVersion {
+        self.inner.model_version()
+    }
+
+    /// 获取所有已加载的发音人名称
+    pub fn voice_names(&self) -> impl Iterator<Item = &str> {
+        self.inner.voice_names()
+    }
+
+    /// 使用自定义的音素词表，参见[`crate::KokoroTts::with_vocabulary`]
+    pub fn with_vocabulary(self, vocabulary: Vocabulary) -> Self {
+        self.inner.with_vocabulary(vocabulary).into()
+    }
+
+    /// 启用严格的音素检查，参见[`crate::KokoroTts::with_strict_tokens`]
+    pub fn with_strict_tokens(self, strict: bool) -> Self {
+        self.inner.with_strict_tokens(strict).into()
+    }
+
+    /// 指定英语的发音变体，参见[`crate::KokoroTts::with_english_variant`]
+    pub fn with_english_variant(self, variant: EnglishVariant) -> Self {
+        self.inner.with_english_variant(variant).into()
+    }
+
+    /// 在标点、换行和空行处插入指定时长的停顿，参见[`crate::KokoroTts::with_pauses`]
+    pub fn with_pauses(self, pauses: Pauses) -> Self {
+        self.inner.with_pauses(pauses).into()
+    }
+
+    /// 启用缓存，参见[`crate::KokoroTts::with_cache`]
+    pub fn with_cache(self, cache: Arc<SynthCache>) -> Self {
+        self.inner.with_cache(cache).into()
+    }
+
+    /// 获取正在使用的缓存
+    pub fn cache(&self) -> Option<&SynthCache> {
+        self.inner.cache()
+    }
+
+    /// 合成语音，返回音频采样和推理耗时
+    pub fn synth<S>(&self, text: S, voice: Voice) -> Result<(Vec<f32>, Duration), KokoroError>
+    where
+        S: AsRef<str>,
+    {
+        block_on(self.inner.synth(text, voice))
+    }
+
+    /// 合成指定时长的语音，参见[`crate::KokoroTts::synth_to_duration`]
+    pub fn synth_to_duration<S>(
+        &self,
+        text: S,
+        voice: Voice,
+        target: Duration,
+    ) -> Result<FittedAudio, KokoroError>
+    where
+        S: AsRef<str>,
+    {
+        block_on(self.inner.synth_to_duration(text, voice, target))
+    }
+
+    /// 合成语音并生成对应的字幕，参见[`crate::KokoroTts::synth_with_captions`]
+    pub fn synth_with_captions<S>(
+        &self,
+        text: S,
+        voice: Voice,
+    ) -> Result<(Vec<f32>, Vec<Cue>), KokoroError>
+    where
+        S: AsRef<str>,
+    {
+        block_on(self.inner.synth_with_captions(text, voice))
+    }
+
+    /// 根据字幕配音，参见[`crate::KokoroTts::dub`]
+    pub fn dub(&self, cues: &[Cue], voice: Voice, max_rate: f32) -> Result<Dubbing, KokoroError> {
+        block_on(self.inner.dub(cues, voice, max_rate))
+    }
+
+    /// 合成多人对话，参见[`crate::KokoroTts::synth_dialogue`]
+    pub fn synth_dialogue(
+        &self,
+        lines: &[Line],
+        cast: &HashMap<String, Speaker>,
+        options: &DialogueOptions,
+    ) -> Result<Dialogue, KokoroError> {
+        block_on(self.inner.synth_dialogue(lines, cast, options))
+    }
+
+    /// 合成有声书，参见[`crate::KokoroTts::synth_audiobook`]
+    pub fn synth_audiobook<P, F>(
+        &self,
+        book: &Book,
+        voice: Voice,
+        options: &AudiobookOptions,
+        dir: P,
+        progress: F,
+    ) -> Result<Vec<ChapterEntry>, KokoroError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Progress),
+    {
+        block_on(
+            self.inner
+                .synth_audiobook(book, voice, options, dir, progress),
+        )
+    }
+
+    /// 开始流式合成
+    ///
+    /// 通过[`SynthSink`]发送文本，通过迭代[`SynthStream`]获取音频，合成在迭代时进行。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use kokoro_tts::{Voice, blocking::KokoroTts};
+    ///
+    /// let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin") else {
+    ///     return;
+    /// };
+    /// let (mut sink, stream) = tts.stream(Voice::Zf003(1.));
+    /// let _ = sink.synth("你好。");
+    /// let _ = sink.synth("今天天气很好。");
+    /// drop(sink);
+    /// for (audio, _) in stream {
+    ///     println!("{}", audio.len());
+    /// }
+    /// ```
+    ///
+    pub fn stream<S>(&self, voice: Voice) -> (SynthSink<S>, SynthStream)
+    where
+        S: AsRef<str> + Send + 'static,
+    {
+        let (sink, stream) = self.inner.stream(voice);
+        (SynthSink { inner: sink }, SynthStream { inner: stream })
+    }
+
+    /// 获取对应的异步合成器
+    pub fn as_async(&self) -> &crate::KokoroTts {
+        &self.inner
+    }
+}
+
+impl From<crate::KokoroTts> for KokoroTts {
+    fn from(inner: crate::KokoroTts) -> Self {
+        Self { inner }
+    }
+}
+
+/// 同步的语音合成发送端
+pub struct SynthSink<S> {
+    inner: crate::SynthSink<S>,
+}
+
+impl<S: AsRef<str>> SynthSink<S> {
+    /// 设置之后发送的文本使用的发音人
+    pub fn set_voice(&mut self, voice: Voice) {
+        self.inner.set_voice(voice)
+    }
+
+    /// 发送合成请求
+    pub fn synth(&mut self, text: S) -> Result<(), KokoroError> {
+        block_on(self.inner.synth(text))
+    }
+
+    /// 取消合成，参见[`crate::SynthSink::cancel`]
+    ///
+    /// 可以在其他线程中调用，用于打断正在迭代的[`SynthStream`]。
+    pub fn cancel(&self) {
+        self.inner.cancel()
+    }
+}
+
+/// 同步的语音合成流，每次迭代合成并返回一段音频；发送端被释放且所有请求处理完后迭代结束
+pub struct SynthStream {
+    inner: crate::SynthStream,
+}
+
+impl SynthStream {
+    /// 获取已返回的音频对应的字幕，参见[`crate::SynthStream::captions`]
+    pub fn captions(&self) -> &[Cue] {
+        self.inner.captions()
+    }
+
+    /// 取出新增的字幕，参见[`crate::SynthStream::take_captions`]
+    pub fn take_captions(&mut self) -> Vec<Cue> {
+        self.inner.take_captions()
+    }
+
+    /// 取出合成失败的原因，参见[`crate::SynthStream::take_error`]
+    pub fn take_error(&mut self) -> Option<KokoroError> {
+        self.inner.take_error()
+    }
+}
+
+impl Iterator for SynthStream {
+    type Item = (Vec<f32>, Duration);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.inner.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{start_synth_session, synthesizer::Synthesized},
+    };
+
+    #[test]
+    fn test_blocking_stream() -> Result<(), KokoroError> {
+        let (sink, stream) = start_synth_session(Voice::Zf001(1.), |text: String, _| async move {
+            Ok(Synthesized::new(vec![0.; text.len()], Duration::ZERO))
+        });
+        let (mut sink, mut stream) = (SynthSink { inner: sink }, SynthStream { inner: stream });
+        sink.synth("a".to_owned())?;
+        sink.synth("bb".to_owned())?;
+        sink.cancel();
+        sink.synth("ccc".to_owned())?;
+        drop(sink);
+        assert_eq!(
+            vec![3],
+            stream.by_ref().map(|(a, _)| a.len()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["ccc"],
+            stream
+                .captions()
+                .iter()
+                .map(|c| &c.text)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
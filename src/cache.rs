@@ -0,0 +1,549 @@
+use {
+    crate::{EnglishVariant, KokoroError, Voice},
+    bincode::{config::standard, decode_from_slice, encode_to_vec},
+    log::warn,
+    std::{
+        collections::{BTreeMap, HashMap},
+        fs::{File, create_dir_all, read, read_dir, remove_file, write},
+        ops::Range,
+        path::{Path, PathBuf},
+        sync::Mutex,
+        time::SystemTime,
+    },
+};
+
+const ENTRY_EXTENSION: &str = "bin";
+
+/// 缓存命中统计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// 音素缓存命中次数
+    pub phoneme_hits: u64,
+    /// 音素缓存未命中次数
+    pub phoneme_misses: u64,
+    /// 音频缓存命中次数（包括磁盘命中）
+    pub audio_hits: u64,
+    /// 音频缓存未命中次数
+    pub audio_misses: u64,
+    /// 音频缓存在磁盘上命中的次数
+    pub disk_hits: u64,
+    /// 内存中的条目数
+    pub entries: usize,
+    /// 内存中的条目占用的字节数
+    pub bytes: usize,
+    /// 磁盘上的条目占用的字节数
+    pub disk_bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Phonemes {
+        fingerprint: u64,
+        text: String,
+        v11: bool,
+        variant: EnglishVariant,
+    },
+    Audio {
+        fingerprint: u64,
+        text: String,
+        voice: String,
+        speed: u32,
+    },
+}
+
+enum Value {
+    Phonemes(String),
+    /// 音频和其中语音所在的采样范围
+    Audio(Vec<f32>, Range<usize>),
+}
+
+struct Entry {
+    value: Value,
+    tick: u64,
+    size: usize,
+}
+
+#[derive(Default)]
+struct Inner {
+    tick: u64,
+    entries: HashMap<Key, Entry>,
+    order: BTreeMap<u64, Key>,
+    bytes: usize,
+    disk_bytes: u64,
+    stats: CacheStats,
+}
+
+impl Inner {
+    fn get(&mut self, key: &Key) -> Option<&Value> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.tick);
+        entry.tick = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(&entry.value)
+    }
+
+    fn insert(&mut self, capacity: usize, key: Key, value: Value) {
+        let size = match &value {
+            Value::Phonemes(p) => p.len(),
+            Value::Audio(a, _) => a.len() * size_of::<f32>(),
+        } + match &key {
+            Key::Phonemes { text, .. } => text.len(),
+            Key::Audio { text, voice, .. } => text.len() + voice.len(),
+        };
+        if size > capacity {
+            return;
+        }
+        if let Some(old) = self.entries.remove(&key) {
+            self.order.remove(&old.tick);
+            self.bytes -= old.size;
+        }
+        while self.bytes + size > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(old) = self.entries.remove(&oldest) {
+                self.bytes -= old.size;
+            }
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                tick: self.tick,
+                size,
+            },
+        );
+        self.bytes += size;
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+/// 音素和音频缓存
+///
+/// 以规范化后的文本、音色和语速为键，在内存中缓存g2p得到的音素和合成出的最终音频，超出容量时淘汰最久未使用的条目。
+/// 可以额外指定一个磁盘目录，用于持久化音频。键中还包含使用缓存的实例的指纹，模型、音色文件或词表等设置不同的实例不会命中彼此的条目，
+/// 这些条目不再使用后会随着淘汰被删除，也可以通过[`Self::clear`]立即清空。
+pub struct SynthCache {
+    capacity: usize,
+    dir: Option<PathBuf>,
+    disk_capacity: u64,
+    inner: Mutex<Inner>,
+}
+
+impl SynthCache {
+    /// 创建一个仅使用内存的缓存
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 内存中所有条目的总字节数上限。
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            dir: None,
+            disk_capacity: 0,
+            inner: Default::default(),
+        }
+    }
+
+    /// 创建一个同时使用内存和磁盘目录的缓存
+    ///
+    /// 磁盘上只保存音频，超出`disk_capacity`时删除最久未使用的文件，目录不存在时会被创建。
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 内存中所有条目的总字节数上限。
+    /// * `dir` - 磁盘缓存目录。
+    /// * `disk_capacity` - 磁盘上所有条目的总字节数上限。
+    pub fn with_dir<P: AsRef<Path>>(
+        capacity: usize,
+        dir: P,
+        disk_capacity: u64,
+    ) -> Result<Self, KokoroError> {
+        create_dir_all(dir.as_ref())?;
+        let cache = Self {
+            capacity,
+            dir: Some(dir.as_ref().to_owned()),
+            disk_capacity,
+            inner: Default::default(),
+        };
+        // 目录中可能有之前运行留下的条目
+        cache.inner.lock().unwrap().disk_bytes = cache.evict()?;
+        Ok(cache)
+    }
+
+    /// 获取命中统计
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats {
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+            disk_bytes: inner.disk_bytes,
+            ..inner.stats
+        }
+    }
+
+    /// 清空内存和磁盘中的所有条目
+    pub fn clear(&self) -> Result<(), KokoroError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clear();
+        inner.disk_bytes = 0;
+        drop(inner);
+        if let Some(dir) = &self.dir {
+            for entry in read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|e| e == ENTRY_EXTENSION) {
+                    remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn phonemes(
+        &self,
+        fingerprint: u64,
+        text: &str,
+        v11: bool,
+        variant: EnglishVariant,
+    ) -> Option<String> {
+        let key = Key::Phonemes {
+            fingerprint,
+            text: normalize(text),
+            v11,
+            variant,
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let ret = match inner.get(&key) {
+            Some(Value::Phonemes(p)) => Some(p.to_owned()),
+            _ => None,
+        };
+        match ret {
+            Some(_) => inner.stats.phoneme_hits += 1,
+            None => inner.stats.phoneme_misses += 1,
+        }
+        ret
+    }
+
+    pub(super) fn insert_phonemes(
+        &self,
+        fingerprint: u64,
+        text: &str,
+        v11: bool,
+        variant: EnglishVariant,
+        phonemes: &str,
+    ) {
+        let key = Key::Phonemes {
+            fingerprint,
+            text: normalize(text),
+            v11,
+            variant,
+        };
+        self.inner
+            .lock()
+            .unwrap()
+            .insert(self.capacity, key, Value::Phonemes(phonemes.to_owned()));
+    }
+
+    /// 返回音频和其中语音所在的采样范围
+    pub(super) fn audio(
+        &self,
+        fingerprint: u64,
+        text: &str,
+        voice: Voice,
+    ) -> Option<(Vec<f32>, Range<usize>)> {
+        let key = audio_key(fingerprint, text, voice);
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(Value::Audio(a, speech)) = inner.get(&key) {
+                let ret = (a.to_owned(), speech.clone());
+                inner.stats.audio_hits += 1;
+                return Some(ret);
+            }
+        }
+
+        let ret = self.dir.as_ref().and_then(|dir| {
+            let path = entry_path(dir, &key);
+            let ret = read_entry(&path, &key)?;
+            // 更新修改时间，淘汰时按最近使用的时间排序
+            if let Err(e) = File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(SystemTime::now()))
+            {
+                warn!("Can't touch cache entry {}: {}", path.display(), e);
+            }
+            Some(ret)
+        });
+        let mut inner = self.inner.lock().unwrap();
+        match &ret {
+            Some((a, speech)) => {
+                inner.stats.audio_hits += 1;
+                inner.stats.disk_hits += 1;
+                inner.insert(
+                    self.capacity,
+                    key,
+                    Value::Audio(a.to_owned(), speech.clone()),
+                );
+            }
+            None => inner.stats.audio_misses += 1,
+        }
+        ret
+    }
+
+    pub(super) fn insert_audio(
+        &self,
+        fingerprint: u64,
+        text: &str,
+        voice: Voice,
+        audio: &[f32],
+        speech: Range<usize>,
+    ) {
+        let key = audio_key(fingerprint, text, voice);
+        if let (
+            Some(dir),
+            Key::Audio {
+                fingerprint,
+                text,
+                voice,
+                speed,
+            },
+        ) = (&self.dir, &key)
+        {
+            let path = entry_path(dir, &key);
+            let entry = (
+                fingerprint,
+                text,
+                voice,
+                speed,
+                audio,
+                (speech.start, speech.end),
+            );
+            match encode_to_vec(entry, standard()) {
+                Ok(data) => {
+                    let old = path.metadata().map_or(0, |m| m.len());
+                    match write(&path, &data) {
+                        Ok(_) => self.grow_disk(data.len() as u64, old),
+                        Err(e) => warn!("Can't write cache entry {}: {}", path.display(), e),
+                    }
+                }
+                Err(e) => warn!("Can't encode cache entry: {}", e),
+            }
+        }
+        self.inner.lock().unwrap().insert(
+            self.capacity,
+            key,
+            Value::Audio(audio.to_owned(), speech),
+        );
+    }
+
+    /// 记录磁盘上新写入的条目，超出上限时淘汰
+    fn grow_disk(&self, size: u64, replaced: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.disk_bytes = (inner.disk_bytes + size).saturating_sub(replaced);
+        if inner.disk_bytes <= self.disk_capacity {
+            return;
+        }
+        drop(inner);
+        match self.evict() {
+            Ok(bytes) => self.inner.lock().unwrap().disk_bytes = bytes,
+            Err(e) => warn!("Can't evict cache entries: {}", e),
+        }
+    }
+
+    /// 按修改时间从旧到新删除磁盘上的条目，直到总大小不超过上限，返回剩余的字节数
+    fn evict(&self) -> Result<u64, KokoroError> {
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+        let mut files = Vec::new();
+        for entry in read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == ENTRY_EXTENSION) {
+                let meta = path.metadata()?;
+                files.push((meta.modified()?, meta.len(), path));
+            }
+        }
+        files.sort();
+        let mut total = files.iter().map(|i| i.1).sum::<u64>();
+        for (_, size, path) in files {
+            if total <= self.disk_capacity {
+                break;
+            }
+            remove_file(path)?;
+            total -= size;
+        }
+        Ok(total)
+    }
+}
+
+/// 规范化文本，合并每一行中连续的空白字符，并去掉空行
+fn normalize(text: &str) -> String {
+    text.lines()
+        .map(|i| i.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|i| !i.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn audio_key(fingerprint: u64, text: &str, voice: Voice) -> Key {
+    Key::Audio {
+        fingerprint,
+        text: normalize(text),
+        voice: voice.name().to_owned(),
+        speed: voice.speed().to_bits(),
+    }
+}
+
+fn entry_path(dir: &Path, key: &Key) -> PathBuf {
+    let mut hasher = Fnv::default();
+    if let Key::Audio {
+        fingerprint,
+        text,
+        voice,
+        speed,
+    } = key
+    {
+        hasher.write(&fingerprint.to_le_bytes());
+        hasher.write(text.as_bytes());
+        hasher.write(&[0]);
+        hasher.write(voice.as_bytes());
+        hasher.write(&speed.to_le_bytes());
+    }
+    dir.join(format!("{:016x}.{}", hasher.finish(), ENTRY_EXTENSION))
+}
+
+type DiskEntry = (u64, String, String, u32, Vec<f32>, (usize, usize));
+
+fn read_entry(path: &Path, key: &Key) -> Option<(Vec<f32>, Range<usize>)> {
+    let data = read(path).ok()?;
+    let ((fingerprint, text, voice, speed, audio, (start, end)), _): (DiskEntry, _) =
+        match decode_from_slice(&data, standard()) {
+            Ok(i) => i,
+            Err(e) => {
+                warn!("Can't decode cache entry {}: {}", path.display(), e);
+                return None;
+            }
+        };
+    let entry = Key::Audio {
+        fingerprint,
+        text,
+        voice,
+        speed,
+    };
+    (entry == *key && start <= end && end <= audio.len()).then_some((audio, start..end))
+}
+
+/// FNV-1a哈希，结果在不同的平台和编译器版本之间保持稳定，可以用于磁盘缓存
+pub(super) struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv {
+    pub(super) fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(super) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// 计算模型数据的指纹
+///
+/// 模型文件通常有几百兆，这里只对长度和均匀分布的若干个数据块取哈希。
+pub(super) fn fingerprint(hasher: &mut Fnv, data: &[u8]) {
+    const BLOCK: usize = 4096;
+    const BLOCKS: usize = 256;
+
+    hasher.write(&(data.len() as u64).to_le_bytes());
+    if data.len() <= BLOCK * BLOCKS {
+        hasher.write(data);
+        return;
+    }
+    let stride = (data.len() - BLOCK) / (BLOCKS - 1);
+    for i in 0..BLOCKS {
+        hasher.write(&data[i * stride..i * stride + BLOCK]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!("你好 世界\nhello", normalize("  你好\t 世界 \n\n hello  "));
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = SynthCache::new(64);
+        let voice = Voice::ZfXiaoxiao(1.);
+        assert_eq!(None, cache.audio(1, "hello", voice));
+        cache.insert_audio(1, "hello", voice, &[0.; 8], 2..6);
+        assert_eq!(Some((vec![0.; 8], 2..6)), cache.audio(1, " hello ", voice));
+        assert_eq!(None, cache.audio(1, "hello", Voice::ZfXiaoxiao(1.2)));
+        // 其他模型或设置的实例不会命中
+        assert_eq!(None, cache.audio(2, "hello", voice));
+
+        cache.insert_phonemes(1, "hi", false, EnglishVariant::American, "hˈI");
+        assert_eq!(
+            Some("hˈI".to_owned()),
+            cache.phonemes(1, "hi", false, EnglishVariant::American)
+        );
+        assert_eq!(
+            None,
+            cache.phonemes(2, "hi", false, EnglishVariant::American)
+        );
+        // 超出容量时淘汰最久未使用的音频
+        cache.insert_audio(1, "world", voice, &[0.; 8], 0..8);
+        assert_eq!(None, cache.audio(1, "hello", voice));
+
+        let stats = cache.stats();
+        assert_eq!((1, 4), (stats.audio_hits, stats.audio_misses));
+        assert_eq!((1, 1), (stats.phoneme_hits, stats.phoneme_misses));
+    }
+
+    #[test]
+    fn test_disk_cache() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("kokoro-cache-{}", std::process::id()));
+        let voice = Voice::ZfXiaoxiao(1.);
+        // 每个条目约400字节，磁盘上只能放下两个
+        let cache = SynthCache::with_dir(0, &dir, 1000)?;
+        for text in ["a", "b", "c"] {
+            cache.insert_audio(1, text, voice, &[0.5; 100], 10..90);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert!(cache.stats().disk_bytes <= 1000);
+        assert_eq!(None, cache.audio(1, "a", voice));
+        assert_eq!(Some((vec![0.5; 100], 10..90)), cache.audio(1, "c", voice));
+
+        // 重新打开目录时计入已有的条目，其他实例的条目不会命中
+        let reopened = SynthCache::with_dir(0, &dir, 1000)?;
+        assert_eq!(cache.stats().disk_bytes, reopened.stats().disk_bytes);
+        assert_eq!(
+            Some((vec![0.5; 100], 10..90)),
+            reopened.audio(1, "b", voice)
+        );
+        assert_eq!(None, reopened.audio(2, "b", voice));
+        reopened.clear()?;
+        assert_eq!(0, reopened.stats().disk_bytes);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,544 @@
+use {
+    crate::{
+        KokoroError, SAMPLE_RATE, Voice,
+        blocking::{KokoroTts, SynthSink},
+        split_sentences,
+    },
+    std::{
+        cell::RefCell,
+        ffi::{CStr, CString, c_char, c_void},
+        fmt::Display,
+        panic::{AssertUnwindSafe, catch_unwind},
+        ptr::{null, null_mut, slice_from_raw_parts_mut},
+        slice::from_raw_parts,
+        thread::{JoinHandle, spawn},
+    },
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// C接口的返回状态
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub enum KokoroStatus {
+    /// 成功
+    Ok = 0,
+    /// 合成失败或库内部出错，通过`kokoro_last_error`获取错误信息
+    Error = 1,
+    /// 参数为空指针或不是有效的UTF-8字符串
+    InvalidArgument = 2,
+    /// 缓冲区不足，所需的采样数已写入`len`
+    BufferTooSmall = 3,
+}
+
+/// 接收音频的回调函数，`samples`只在回调期间有效
+pub type KokoroAudioCallback =
+    Option<unsafe extern "C" fn(samples: *const f32, len: usize, user_data: *mut c_void)>;
+
+/// 语音合成引擎
+pub struct KokoroEngine {
+    tts: KokoroTts,
+    voices: Vec<CString>,
+}
+
+/// 流式合成会话，音频在后台线程中合成并回调
+pub struct KokoroStream {
+    sink: SynthSink<String>,
+    thread: JoinHandle<Option<KokoroError>>,
+}
+
+/// 调用方传入的用户数据，由调用方保证可以在其他线程使用
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+fn set_error<E: Display>(error: E) {
+    let message = error.to_string().replace('\0', "");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+/// 捕获库内部的panic，避免其越过C接口导致宿主进程中止，panic时记录错误信息并返回`fallback`
+fn guard<T, F: FnOnce() -> T>(fallback: T, f: F) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|i| i.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        set_error(format!("Kokoro panicked: {}", message));
+        fallback
+    })
+}
+
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> Option<&'a str> {
+    if s.is_null() {
+        set_error(format!("{} is null", name));
+        return None;
+    }
+    match unsafe { CStr::from_ptr(s) }.to_str() {
+        Ok(s) => Some(s),
+        Err(e) => {
+            set_error(format!("{} is not valid UTF-8: {}", name, e));
+            None
+        }
+    }
+}
+
+unsafe fn to_voice(voice: *const c_char, speed: f32) -> Option<Voice> {
+    let name = unsafe { to_str(voice, "voice") }?;
+    Voice::from_name(name, speed).map_err(set_error).ok()
+}
+
+fn new_engine(tts: Result<KokoroTts, KokoroError>) -> *mut KokoroEngine {
+    let tts = match tts {
+        Ok(t) => t,
+        Err(e) => {
+            set_error(e);
+            return null_mut();
+        }
+    };
+    let mut voices = tts
+        .voice_names()
+        .filter_map(|i| CString::new(i).ok())
+        .collect::<Vec<_>>();
+    voices.sort_unstable();
+    Box::into_raw(Box::new(KokoroEngine { tts, voices }))
+}
+
+/// 获取当前线程最后一次失败的错误信息，没有错误时返回空指针
+///
+/// 返回的字符串在当前线程下一次调用失败之前有效。
+#[unsafe(no_mangle)]
+pub extern "C" fn kokoro_last_error() -> *const c_char {
+    guard(null(), || {
+        LAST_ERROR.with(|e| e.borrow().as_ref().map_or(null(), |e| e.as_ptr()))
+    })
+}
+
+/// 合成音频的采样率
+#[unsafe(no_mangle)]
+pub extern "C" fn kokoro_sample_rate() -> u32 {
+    SAMPLE_RATE
+}
+
+/// 从模型文件和发音人文件创建引擎，失败时返回空指针
+///
+/// # Safety
+///
+/// `model_path`和`voices_path`必须是以`\0`结尾的字符串。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_engine_new(
+    model_path: *const c_char,
+    voices_path: *const c_char,
+) -> *mut KokoroEngine {
+    guard(null_mut(), || {
+        let Some(model_path) = (unsafe { to_str(model_path, "model_path") }) else {
+            return null_mut();
+        };
+        let Some(voices_path) = (unsafe { to_str(voices_path, "voices_path") }) else {
+            return null_mut();
+        };
+        new_engine(KokoroTts::new(model_path, voices_path))
+    })
+}
+
+/// 从内存中的模型和发音人数据创建引擎，失败时返回空指针
+///
+/// # Safety
+///
+/// `model`和`voices`必须分别指向长度为`model_len`和`voices_len`的有效内存，函数返回后即可释放。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_engine_new_from_bytes(
+    model: *const u8,
+    model_len: usize,
+    voices: *const u8,
+    voices_len: usize,
+) -> *mut KokoroEngine {
+    guard(null_mut(), || {
+        if model.is_null() || voices.is_null() {
+            set_error("model or voices is null");
+            return null_mut();
+        }
+        let model = unsafe { from_raw_parts(model, model_len) };
+        let voices = unsafe { from_raw_parts(voices, voices_len) };
+        new_engine(KokoroTts::new_from_bytes(model, voices))
+    })
+}
+
+/// 释放引擎，所有流式合成会话必须已经结束
+///
+/// # Safety
+///
+/// `engine`必须是`kokoro_engine_new`系列函数返回的指针或空指针，释放后不能再使用。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_engine_free(engine: *mut KokoroEngine) {
+    guard((), || {
+        if !engine.is_null() {
+            drop(unsafe { Box::from_raw(engine) });
+        }
+    })
+}
+
+/// 发音人数量
+///
+/// # Safety
+///
+/// `engine`必须是有效的引擎指针。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_voice_count(engine: *const KokoroEngine) -> usize {
+    guard(0, || {
+        unsafe { engine.as_ref() }.map_or(0, |e| e.voices.len())
+    })
+}
+
+/// 按名称排序后第`index`个发音人的名称，越界时返回空指针
+///
+/// 返回的字符串在引擎释放之前有效。
+///
+/// # Safety
+///
+/// `engine`必须是有效的引擎指针。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_voice_name(
+    engine: *const KokoroEngine,
+    index: usize,
+) -> *const c_char {
+    guard(null(), || {
+        unsafe { engine.as_ref() }
+            .and_then(|e| e.voices.get(index))
+            .map_or(null(), |i| i.as_ptr())
+    })
+}
+
+/// 检查参数并合成整段文本
+unsafe fn synth(
+    engine: *mut KokoroEngine,
+    text: *const c_char,
+    voice: *const c_char,
+    speed: f32,
+) -> Result<Vec<f32>, KokoroStatus> {
+    let Some(engine) = (unsafe { engine.as_ref() }) else {
+        set_error("engine is null");
+        return Err(KokoroStatus::InvalidArgument);
+    };
+    let (Some(text), Some(voice)) = (unsafe { to_str(text, "text") }, unsafe {
+        to_voice(voice, speed)
+    }) else {
+        return Err(KokoroStatus::InvalidArgument);
+    };
+    match engine.tts.synth(text, voice) {
+        Ok((audio, _)) => Ok(audio),
+        Err(e) => {
+            set_error(e);
+            Err(KokoroStatus::Error)
+        }
+    }
+}
+
+/// 合成语音并写入调用方提供的缓冲区
+///
+/// 成功时`len`为写入的采样数；缓冲区不足时返回`KOKORO_STATUS_BUFFER_TOO_SMALL`，`len`为所需的采样数。
+/// 合成结果不会保留，事先不知道长度时使用`kokoro_synth_alloc`可以避免重复合成，较长的文本建议使用`kokoro_synth_with_callback`。
+///
+/// # Safety
+///
+/// `engine`必须是有效的引擎指针，`text`和`voice`必须是以`\0`结尾的字符串，
+/// `buffer`必须指向至少`capacity`个`float`，`len`必须是有效的指针。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_synth(
+    engine: *mut KokoroEngine,
+    text: *const c_char,
+    voice: *const c_char,
+    speed: f32,
+    buffer: *mut f32,
+    capacity: usize,
+    len: *mut usize,
+) -> KokoroStatus {
+    guard(KokoroStatus::Error, || {
+        let Some(len) = (unsafe { len.as_mut() }) else {
+            set_error("len is null");
+            return KokoroStatus::InvalidArgument;
+        };
+        let audio = match unsafe { synth(engine, text, voice, speed) } {
+            Ok(audio) => audio,
+            Err(status) => return status,
+        };
+        *len = audio.len();
+        if buffer.is_null() || capacity < audio.len() {
+            set_error(format!("Buffer too small, {} samples needed", audio.len()));
+            return KokoroStatus::BufferTooSmall;
+        }
+        unsafe { buffer.copy_from_nonoverlapping(audio.as_ptr(), audio.len()) };
+        KokoroStatus::Ok
+    })
+}
+
+/// 合成语音并返回由库分配的缓冲区
+///
+/// 成功时`samples`指向合成的音频，`len`为采样数，使用完后必须通过`kokoro_audio_free`释放。
+///
+/// # Safety
+///
+/// `engine`必须是有效的引擎指针，`text`和`voice`必须是以`\0`结尾的字符串，`samples`和`len`必须是有效的指针。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_synth_alloc(
+    engine: *mut KokoroEngine,
+    text: *const c_char,
+    voice: *const c_char,
+    speed: f32,
+    samples: *mut *mut f32,
+    len: *mut usize,
+) -> KokoroStatus {
+    guard(KokoroStatus::Error, || {
+        let (Some(samples), Some(len)) = (unsafe { samples.as_mut() }, unsafe { len.as_mut() })
+        else {
+            set_error("samples or len is null");
+            return KokoroStatus::InvalidArgument;
+        };
+        let audio = match unsafe { synth(engine, text, voice, speed) } {
+            Ok(audio) => audio.into_boxed_slice(),
+            Err(status) => return status,
+        };
+        *len = audio.len();
+        *samples = Box::into_raw(audio).cast();
+        KokoroStatus::Ok
+    })
+}
+
+/// 释放`kokoro_synth_alloc`返回的音频
+///
+/// # Safety
+///
+/// `samples`和`len`必须是`kokoro_synth_alloc`返回的值，或者`samples`为空指针；释放后不能再使用。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_audio_free(samples: *mut f32, len: usize) {
+    guard((), || {
+        if !samples.is_null() {
+            drop(unsafe { Box::from_raw(slice_from_raw_parts_mut(samples, len)) });
+        }
+    })
+}
+
+/// 逐句合成语音，每合成完一句就调用一次`callback`，所有句子合成完后返回
+///
+/// # Safety
+///
+/// `engine`必须是有效的引擎指针，`text`和`voice`必须是以`\0`结尾的字符串。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_synth_with_callback(
+    engine: *mut KokoroEngine,
+    text: *const c_char,
+    voice: *const c_char,
+    speed: f32,
+    callback: KokoroAudioCallback,
+    user_data: *mut c_void,
+) -> KokoroStatus {
+    guard(KokoroStatus::Error, || {
+        let Some(engine) = (unsafe { engine.as_ref() }) else {
+            set_error("engine is null");
+            return KokoroStatus::InvalidArgument;
+        };
+        let (Some(text), Some(voice)) = (unsafe { to_str(text, "text") }, unsafe {
+            to_voice(voice, speed)
+        }) else {
+            return KokoroStatus::InvalidArgument;
+        };
+        let Some(callback) = callback else {
+            set_error("callback is null");
+            return KokoroStatus::InvalidArgument;
+        };
+
+        for sentence in split_sentences(text) {
+            match engine.tts.synth(sentence, voice) {
+                Ok((audio, _)) => unsafe { callback(audio.as_ptr(), audio.len(), user_data) },
+                Err(e) => {
+                    set_error(e);
+                    return KokoroStatus::Error;
+                }
+            }
+        }
+        KokoroStatus::Ok
+    })
+}
+
+/// 开始流式合成会话，失败时返回空指针
+///
+/// 通过`kokoro_stream_synth`发送的文本按顺序合成，`callback`在会话的后台线程中被调用。
+///
+/// # Safety
+///
+/// `engine`必须是有效的引擎指针，并且在会话结束之前不能释放；`voice`必须是以`\0`结尾的字符串；
+/// `user_data`必须可以在其他线程中使用。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_stream_start(
+    engine: *mut KokoroEngine,
+    voice: *const c_char,
+    speed: f32,
+    callback: KokoroAudioCallback,
+    user_data: *mut c_void,
+) -> *mut KokoroStream {
+    guard(null_mut(), || {
+        let Some(engine) = (unsafe { engine.as_ref() }) else {
+            set_error("engine is null");
+            return null_mut();
+        };
+        let Some(voice) = (unsafe { to_voice(voice, speed) }) else {
+            return null_mut();
+        };
+        let Some(callback) = callback else {
+            set_error("callback is null");
+            return null_mut();
+        };
+
+        let (sink, stream) = engine.tts.stream(voice);
+        let user_data = UserData(user_data);
+        let thread = spawn(move || {
+            let mut stream = stream;
+            while let Some((audio, _)) = stream.next() {
+                // 不使用字幕，取出后丢弃以免长时间的会话占用越来越多的内存
+                stream.take_captions();
+                unsafe { callback(audio.as_ptr(), audio.len(), user_data.get()) };
+            }
+            stream.take_error()
+        });
+        Box::into_raw(Box::new(KokoroStream { sink, thread }))
+    })
+}
+
+/// 向流式合成会话发送一段文本
+///
+/// # Safety
+///
+/// `stream`必须是有效的会话指针，`text`必须是以`\0`结尾的字符串。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_stream_synth(
+    stream: *mut KokoroStream,
+    text: *const c_char,
+) -> KokoroStatus {
+    guard(KokoroStatus::Error, || {
+        let Some(stream) = (unsafe { stream.as_mut() }) else {
+            set_error("stream is null");
+            return KokoroStatus::InvalidArgument;
+        };
+        let Some(text) = (unsafe { to_str(text, "text") }) else {
+            return KokoroStatus::InvalidArgument;
+        };
+        for sentence in split_sentences(text) {
+            if let Err(e) = stream.sink.synth(sentence) {
+                set_error(e);
+                return KokoroStatus::Error;
+            }
+        }
+        KokoroStatus::Ok
+    })
+}
+
+/// 等待已发送的文本全部合成并回调完毕，然后释放会话
+///
+/// 某段文本合成失败时之后的文本不再合成，返回`KOKORO_STATUS_ERROR`。
+///
+/// # Safety
+///
+/// `stream`必须是有效的会话指针，释放后不能再使用；不能在回调函数中调用。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_stream_finish(stream: *mut KokoroStream) -> KokoroStatus {
+    guard(KokoroStatus::Error, || {
+        if stream.is_null() {
+            set_error("stream is null");
+            return KokoroStatus::InvalidArgument;
+        }
+        let KokoroStream { sink, thread } = *unsafe { Box::from_raw(stream) };
+        drop(sink);
+        match thread.join() {
+            Ok(None) => KokoroStatus::Ok,
+            Ok(Some(e)) => {
+                set_error(e);
+                KokoroStatus::Error
+            }
+            Err(_) => {
+                set_error("stream thread panicked");
+                KokoroStatus::Error
+            }
+        }
+    })
+}
+
+/// 立即停止流式合成并释放会话，函数返回后不会再调用回调函数
+///
+/// # Safety
+///
+/// `stream`必须是有效的会话指针，释放后不能再使用；不能在回调函数中调用。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kokoro_stream_stop(stream: *mut KokoroStream) {
+    guard((), || {
+        if stream.is_null() {
+            return;
+        }
+        let KokoroStream { sink, thread } = *unsafe { Box::from_raw(stream) };
+        sink.cancel();
+        drop(sink);
+        let _ = thread.join();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capi_errors() {
+        let engine = unsafe { kokoro_engine_new(c"missing.onnx".as_ptr(), null()) };
+        assert!(engine.is_null());
+        let error = unsafe { CStr::from_ptr(kokoro_last_error()) };
+        assert_eq!(c"voices_path is null", error);
+        assert_eq!(0, unsafe { kokoro_voice_count(null()) });
+
+        let mut len = 0;
+        let status = unsafe {
+            kokoro_synth(
+                null_mut(),
+                c"hello".as_ptr(),
+                c"af_heart".as_ptr(),
+                1.,
+                null_mut(),
+                0,
+                &mut len,
+            )
+        };
+        assert_eq!(KokoroStatus::InvalidArgument, status);
+
+        let (mut samples, mut len) = (null_mut(), 0);
+        let status = unsafe {
+            kokoro_synth_alloc(
+                null_mut(),
+                c"hello".as_ptr(),
+                c"af_heart".as_ptr(),
+                1.,
+                &mut samples,
+                &mut len,
+            )
+        };
+        assert_eq!(KokoroStatus::InvalidArgument, status);
+        assert!(samples.is_null());
+        unsafe { kokoro_audio_free(samples, len) };
+    }
+
+    #[test]
+    fn test_guard() {
+        assert_eq!(
+            KokoroStatus::Error,
+            guard(KokoroStatus::Error, || panic!("index out of bounds"))
+        );
+        let error = unsafe { CStr::from_ptr(kokoro_last_error()) };
+        assert_eq!(c"Kokoro panicked: index out of bounds", error);
+        assert_eq!(1, guard(0, || 1));
+    }
+}
@@ -0,0 +1,220 @@
+use {
+    crate::{
+        Cue, KokoroError, SAMPLE_RATE, Voice,
+        audio::{from_samples, to_samples},
+        audiobook::join_line,
+        resample,
+        synthesizer::Synthesized,
+        to_wav, to_wav_stereo,
+    },
+    std::time::Duration,
+};
+
+/// 对话脚本中的一句台词
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// 说话人
+    pub speaker: String,
+    /// 台词
+    pub text: String,
+}
+
+/// 解析对话脚本
+///
+/// 每一句台词以`说话人: 台词`开始，冒号也可以是全角的`：`，之后的行接在上一句台词后面。
+/// 说话人中不能有空白和数字，因此`比分是3:2`这样的行也会接在上一句台词后面。
+/// 空行和以`#`开头的注释行会被忽略。
+pub fn parse_script(script: &str) -> Result<Vec<Line>, KokoroError> {
+    let mut lines = Vec::<Line>::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match split_speaker(line) {
+            Some((speaker, text)) => lines.push(Line {
+                speaker: speaker.to_owned(),
+                text: text.trim().to_owned(),
+            }),
+            _ => {
+                let last = lines.last_mut().ok_or_else(|| {
+                    KokoroError::ScriptInvalid(format!("line {} has no speaker", number + 1))
+                })?;
+                join_line(&mut last.text, line);
+            }
+        }
+    }
+    lines.retain(|i| !i.text.is_empty());
+    Ok(lines)
+}
+
+/// 分出行首的说话人，冒号之前为空或含有空白、数字时不是说话人
+fn split_speaker(line: &str) -> Option<(&str, &str)> {
+    let (speaker, text) = line.split_once([':', '：'])?;
+    let speaker = speaker.trim_end();
+    (!speaker.is_empty() && !speaker.chars().any(|c| c.is_whitespace() || c.is_numeric()))
+        .then_some((speaker, text))
+}
+
+/// 说话人使用的发音人和在立体声中的位置
+#[derive(Clone, Copy, Debug)]
+pub struct Speaker {
+    /// 发音人，包括语速
+    pub voice: Voice,
+    /// 立体声中的位置，-1为最左，0为居中，1为最右，只在[`DialogueOptions::stereo`]时有效
+    pub pan: f32,
+}
+
+impl From<Voice> for Speaker {
+    fn from(voice: Voice) -> Self {
+        Self { voice, pan: 0. }
+    }
+}
+
+/// 对话合成的设置
+#[derive(Clone, Debug)]
+pub struct DialogueOptions {
+    /// 两句台词之间的停顿
+    pub turn_gap: Duration,
+    /// 是否输出立体声，按照[`Speaker::pan`]放置每个说话人
+    pub stereo: bool,
+}
+
+impl Default for DialogueOptions {
+    fn default() -> Self {
+        Self {
+            turn_gap: Duration::from_millis(400),
+            stereo: false,
+        }
+    }
+}
+
+/// 合成的对话，参见[`crate::KokoroTts::synth_dialogue`]
+#[derive(Clone, Debug, Default)]
+pub struct Dialogue {
+    /// 单声道时只有一个声道，立体声时依次为左、右声道
+    pub channels: Vec<Vec<f32>>,
+    /// 每句台词的字幕，文本为`说话人: 台词`
+    pub captions: Vec<Cue>,
+}
+
+impl Dialogue {
+    pub(super) fn new(stereo: bool) -> Self {
+        Self {
+            channels: vec![Vec::new(); if stereo { 2 } else { 1 }],
+            captions: Vec::new(),
+        }
+    }
+
+    /// 在音轨末尾加入一句台词，去掉首尾的静音，与上一句之间停顿`gap`
+    pub(super) fn push(
+        &mut self,
+        line: &Line,
+        speaker: &Speaker,
+        synthesized: &Synthesized,
+        gap: Duration,
+    ) {
+        let mut start = self.channels[0].len();
+        if !self.captions.is_empty() {
+            start += to_samples(gap);
+        }
+        let pan = speaker.pan.clamp(-1., 1.);
+        // 单声道时不衰减，立体声时靠近一侧会降低另一侧的音量
+        let gains = match self.channels.len() {
+            1 => vec![1.],
+            _ => vec![(1. - pan).min(1.), (1. + pan).min(1.)],
+        };
+        // 模型输出的首尾静音会让实际的停顿比`gap`更长
+        let speech = &synthesized.audio[synthesized.speech.clone()];
+        for (channel, gain) in self.channels.iter_mut().zip(gains) {
+            channel.resize(start, 0.);
+            channel.extend(speech.iter().map(|i| i * gain));
+        }
+
+        self.captions.push(Cue {
+            start: from_samples(start),
+            end: from_samples(start + speech.len()),
+            text: format!("{}: {}", line.speaker, line.text),
+        });
+    }
+
+    /// 编码为WAV文件
+    pub fn to_wav(&self, sample_rate: u32) -> Vec<u8> {
+        let channels = self
+            .channels
+            .iter()
+            .map(|i| resample(i, SAMPLE_RATE, sample_rate))
+            .collect::<Vec<_>>();
+        match &channels[..] {
+            [left, right] => to_wav_stereo(left, right, sample_rate),
+            [mono, ..] => to_wav(mono, sample_rate),
+            [] => to_wav(&[], sample_rate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() -> Result<(), KokoroError> {
+        let lines = parse_script(
+            "# 第一期\n主持人：大家好，\n欢迎收听。\n\nGuest: Hello!\nThe score was 3:2.\n\
+             比分是３：２，\n10:30 见。\n主持人: 今天聊什么？",
+        )?;
+        assert_eq!(
+            vec![
+                ("主持人", "大家好，欢迎收听。"),
+                ("Guest", "Hello! The score was 3:2.比分是３：２，10:30 见。"),
+                ("主持人", "今天聊什么？")
+            ],
+            lines
+                .iter()
+                .map(|i| (i.speaker.as_str(), i.text.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert!(parse_script("没有说话人\nA: 你好").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dialogue_push() {
+        let line = |speaker: &str| Line {
+            speaker: speaker.to_owned(),
+            text: "你好".to_owned(),
+        };
+        let mut dialogue = Dialogue::new(true);
+        let left = Speaker {
+            voice: Voice::Zf003(1.),
+            pan: -1.,
+        };
+        dialogue.push(
+            &line("A"),
+            &left,
+            &Synthesized::new(vec![0.5; 2400], Duration::ZERO),
+            Duration::from_millis(500),
+        );
+        // 首尾各有0.05秒静音
+        let mut audio = vec![0.; 3600];
+        audio[1200..2400].fill(0.5);
+        dialogue.push(
+            &line("B"),
+            &Voice::Zm009(1.).into(),
+            &Synthesized {
+                audio,
+                took: Duration::ZERO,
+                speech: 1200..2400,
+            },
+            Duration::from_millis(500),
+        );
+        assert_eq!(2, dialogue.channels.len());
+        assert_eq!(2400 + 12000 + 1200, dialogue.channels[0].len());
+        assert_eq!(0.5, dialogue.channels[0][0]);
+        assert_eq!(0., dialogue.channels[1][0]);
+        assert_eq!(0.5, dialogue.channels[1][14400]);
+        assert_eq!(Duration::from_millis(600), dialogue.captions[1].start);
+        assert_eq!(Duration::from_millis(650), dialogue.captions[1].end);
+        assert_eq!("B: 你好", dialogue.captions[1].text);
+    }
+}
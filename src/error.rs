@@ -1,7 +1,8 @@
-use crate::G2PError;
+use crate::{G2PError, ModelVersion};
 use bincode::error::DecodeError;
 use ndarray::ShapeError;
 use ort::Error as OrtError;
+use serde_json::Error as JsonError;
 use std::{
     error::Error,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
@@ -12,13 +13,21 @@ use std::{
 #[derive(Debug)]
 pub enum KokoroError {
     Decode(DecodeError),
+    DocumentInvalid(String),
     G2P(G2PError),
     Io(IoError),
+    Json(JsonError),
     ModelReleased,
+    ModelUnsupported(String),
     Ort(OrtError),
+    ScriptInvalid(String),
     Send(String),
     Shape(ShapeError),
+    SubtitleInvalid(String),
     SystemTime(SystemTimeError),
+    UnknownPhoneme(char, usize),
+    VocabularyInvalid(String),
+    VoiceModelMismatch(String, ModelVersion),
     VoiceNotFound(String),
     VoiceVersionInvalid(String),
 }
@@ -28,13 +37,25 @@ impl Display for KokoroError {
         write!(f, "KokoroError: ")?;
         match self {
             Self::Decode(e) => Display::fmt(e, f),
+            Self::DocumentInvalid(msg) => write!(f, "DocumentInvalid({})", msg),
             Self::G2P(e) => Display::fmt(e, f),
             Self::Io(e) => Display::fmt(e, f),
+            Self::Json(e) => Display::fmt(e, f),
             Self::Ort(e) => Display::fmt(e, f),
             Self::ModelReleased => write!(f, "ModelReleased"),
+            Self::ModelUnsupported(msg) => write!(f, "ModelUnsupported({})", msg),
+            Self::ScriptInvalid(msg) => write!(f, "ScriptInvalid({})", msg),
             Self::Send(e) => Display::fmt(e, f),
             Self::Shape(e) => Display::fmt(e, f),
+            Self::SubtitleInvalid(msg) => write!(f, "SubtitleInvalid({})", msg),
             Self::SystemTime(e) => Display::fmt(e, f),
+            Self::UnknownPhoneme(symbol, position) => {
+                write!(f, "UnknownPhoneme({:?} at {})", symbol, position)
+            }
+            Self::VocabularyInvalid(msg) => write!(f, "VocabularyInvalid({})", msg),
+            Self::VoiceModelMismatch(name, version) => {
+                write!(f, "VoiceModelMismatch({} with {} model)", name, version)
+            }
             Self::VoiceNotFound(name) => write!(f, "VoiceNotFound({})", name),
             Self::VoiceVersionInvalid(msg) => write!(f, "VoiceVersionInvalid({})", msg),
         }
@@ -55,6 +76,12 @@ impl From<DecodeError> for KokoroError {
     }
 }
 
+impl From<JsonError> for KokoroError {
+    fn from(value: JsonError) -> Self {
+        Self::Json(value)
+    }
+}
+
 impl From<OrtError> for KokoroError {
     fn from(value: OrtError) -> Self {
         Self::Ort(value)
@@ -1,16 +1,25 @@
 /// 文本到国际音标的转换
+#[cfg(feature = "use-cmudict")]
+mod en;
 mod v10;
 mod v11;
 
-use super::PinyinError;
+use super::{PinyinError, pinyin_to_ipa};
 use chinese_number::{ChineseCase, ChineseCountMethod, ChineseVariant, NumberToChinese};
 #[cfg(feature = "use-cmudict")]
 use cmudict_fast::{Cmudict, Error as CmudictError};
+use jieba_rs::Jieba;
 use pinyin::ToPinyin;
-use regex::{Captures, Error as RegexError, Regex};
+use regex::{Error as RegexError, Regex};
+#[cfg(feature = "use-cmudict")]
+use std::str::FromStr;
 use std::{
     error::Error,
+    ffi::{CStr, CString, NulError, c_char, c_int},
     fmt::{Display, Formatter, Result as FmtResult},
+    ops::Range,
+    str::Utf8Error,
+    sync::{LazyLock, Mutex, OnceLock},
 };
 
 #[derive(Debug)]
@@ -18,12 +27,10 @@ pub enum G2PError {
     #[cfg(feature = "use-cmudict")]
     CmudictError(CmudictError),
     EnptyData,
-    #[cfg(not(feature = "use-cmudict"))]
-    Nul(std::ffi::NulError),
+    Nul(NulError),
     Pinyin(PinyinError),
     Regex(RegexError),
-    #[cfg(not(feature = "use-cmudict"))]
-    Utf8(std::str::Utf8Error),
+    Utf8(Utf8Error),
 }
 
 impl Display for G2PError {
@@ -33,11 +40,9 @@ impl Display for G2PError {
             #[cfg(feature = "use-cmudict")]
             Self::CmudictError(e) => Display::fmt(e, f),
             Self::EnptyData => Display::fmt("EmptyData", f),
-            #[cfg(not(feature = "use-cmudict"))]
             Self::Nul(e) => Display::fmt(e, f),
             Self::Pinyin(e) => Display::fmt(e, f),
             Self::Regex(e) => Display::fmt(e, f),
-            #[cfg(not(feature = "use-cmudict"))]
             Self::Utf8(e) => Display::fmt(e, f),
         }
     }
@@ -64,97 +69,105 @@ impl From<CmudictError> for G2PError {
     }
 }
 
-#[cfg(not(feature = "use-cmudict"))]
-impl From<std::ffi::NulError> for G2PError {
-    fn from(value: std::ffi::NulError) -> Self {
+impl From<NulError> for G2PError {
+    fn from(value: NulError) -> Self {
         Self::Nul(value)
     }
 }
 
-#[cfg(not(feature = "use-cmudict"))]
-impl From<std::str::Utf8Error> for G2PError {
-    fn from(value: std::str::Utf8Error) -> Self {
+impl From<Utf8Error> for G2PError {
+    fn from(value: Utf8Error) -> Self {
         Self::Utf8(value)
     }
 }
 
-fn word2ipa_zh(word: &str) -> Result<String, G2PError> {
-    let iter = word.chars().map(|i| match i.to_pinyin() {
-        None => Ok(i.to_string()),
-        Some(p) => v10::py2ipa(p.with_tone_num_end()),
-    });
+/// 英语的发音变体
+///
+/// 对应misaki的`a`（美式）和`b`（英式）两套音系，Kokoro的英语发音人就是按这两套音系训练的。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnglishVariant {
+    /// 美式英语（General American），`af_`/`am_`发音人使用
+    American,
+    /// 英式英语（Received Pronunciation），`bf_`/`bm_`发音人使用
+    British,
+}
 
-    let mut result = String::new();
-    for i in iter {
-        result.push_str(&i?);
-    }
-    Ok(result)
+unsafe extern "C" {
+    fn TextToPhonemes(text: *const c_char) -> *const c_char;
+    fn Initialize(data_dictlist: *const c_char);
+    fn SetAmerican(enable: c_int);
+    fn SetFullRules(enable: c_int);
 }
 
-#[cfg(feature = "use-cmudict")]
-fn word2ipa_en(word: &str) -> Result<String, G2PError> {
-    use super::{arpa_to_ipa, letters_to_ipa};
-    use std::{
-        io::{Error as IoError, ErrorKind},
-        str::FromStr,
-        sync::LazyLock,
-    };
+// espeak的状态（包括输出缓冲区）都是全局的，调用期间必须独占
+static ESPEAK: LazyLock<Mutex<()>> = LazyLock::new(|| {
+    static DATA: &[u8] = include_bytes!("../dict/espeak.dict");
+    unsafe { Initialize(DATA.as_ptr() as _) };
+    Mutex::new(())
+});
 
-    fn get_cmudict<'a>() -> Result<&'a Cmudict, CmudictError> {
-        static CMUDICT: LazyLock<Result<Cmudict, CmudictError>> =
-            LazyLock::new(|| Cmudict::from_str(include_str!("../dict/cmudict.dict")));
-        CMUDICT.as_ref().map_err(|i| match i {
-            CmudictError::IoErr(e) => CmudictError::IoErr(IoError::new(ErrorKind::Other, e)),
-            CmudictError::InvalidLine(e) => CmudictError::InvalidLine(*e),
-            CmudictError::RuleParseError(e) => CmudictError::RuleParseError(e.clone()),
-        })
+/// 返回音素和每个汉字的带调拼音
+fn word2ipa_zh(word: &str) -> Result<(String, Vec<String>), G2PError> {
+    let mut result = String::new();
+    let mut pinyins = Vec::new();
+    for i in word.chars() {
+        match i.to_pinyin() {
+            None => result.push(i),
+            Some(p) => {
+                result.push_str(&v10::py2ipa(p.with_tone_num_end())?);
+                pinyins.push(p.with_tone_num_end().to_owned());
+            }
+        }
     }
+    Ok((result, pinyins))
+}
 
-    let Some(rules) = get_cmudict()?.get(word) else {
-        return Ok(letters_to_ipa(word));
-    };
-    if rules.is_empty() {
-        return Ok(word.to_owned());
-    }
-    let i = rand::random_range(0..rules.len());
-    let result = rules[i]
-        .pronunciation()
-        .iter()
-        .map(|i| arpa_to_ipa(&i.to_string()).unwrap_or_default())
-        .collect::<String>();
-    Ok(result)
+fn espeak_word2ipa(word: &str, variant: EnglishVariant) -> Result<String, G2PError> {
+    espeak_translate(word, variant, false)
 }
 
-#[cfg(not(feature = "use-cmudict"))]
-fn word2ipa_en(word: &str) -> Result<String, G2PError> {
+/// `full_rules`为真时额外匹配只为未登录词准备的规则类型（音节数、重音、加减分等）
+fn espeak_translate(
+    word: &str,
+    variant: EnglishVariant,
+    full_rules: bool,
+) -> Result<String, G2PError> {
     use super::letters_to_ipa;
-    use std::{
-        ffi::{CStr, CString, c_char},
-        sync::Once,
-    };
 
     if word.chars().count() < 4 && word.chars().all(|c| c.is_ascii_uppercase()) {
         return Ok(letters_to_ipa(word));
     }
 
-    unsafe extern "C" {
-        fn TextToPhonemes(text: *const c_char) -> *const ::std::os::raw::c_char;
-        fn Initialize(data_dictlist: *const c_char);
-    }
-
+    let word = CString::new(word.to_lowercase())?;
+    let _guard = ESPEAK.lock().unwrap_or_else(|e| e.into_inner());
     unsafe {
-        static INIT: Once = Once::new();
-        INIT.call_once(|| {
-            static DATA: &[u8] = include_bytes!("../dict/espeak.dict");
-            Initialize(DATA.as_ptr() as _);
-        });
-
-        let word = CString::new(word.to_lowercase())?.into_raw() as *const c_char;
-        let res = TextToPhonemes(word);
+        SetAmerican((variant == EnglishVariant::American) as _);
+        SetFullRules(full_rules as _);
+        let res = TextToPhonemes(word.as_ptr());
         Ok(CStr::from_ptr(res).to_str()?.to_string())
     }
 }
 
+/// cmudict中没有收录的单词使用espeak的美式发音规则推测读音
+///
+/// 全部大写、没有元音字母或者规则无法给出重音的单词（通常是缩写）仍然逐个字母拼读。
+#[cfg(feature = "use-cmudict")]
+fn oov_word2ipa(word: &str) -> Result<String, G2PError> {
+    use super::letters_to_ipa;
+
+    if word.chars().all(|c| c.is_ascii_uppercase())
+        || !word.chars().any(|c| "aeiouyAEIOUY".contains(c))
+    {
+        return Ok(letters_to_ipa(word));
+    }
+    let ipa = espeak_translate(word, EnglishVariant::American, true)?;
+    if ipa.contains('ˈ') {
+        Ok(ipa)
+    } else {
+        Ok(letters_to_ipa(word))
+    }
+}
+
 fn to_half_shape(text: &str) -> String {
     let mut result = String::with_capacity(text.len() * 2); // 预分配合理空间
     let mut chars = text.chars().peekable();
@@ -182,93 +195,635 @@ fn to_half_shape(text: &str) -> String {
     result
 }
 
-fn num_repr(text: &str) -> Result<String, G2PError> {
-    let regex = Regex::new(r#"\d+(\.\d+)?"#)?;
-    Ok(regex
-        .replace(text, |caps: &Captures| {
-            let text = &caps[0];
-            if let Ok(num) = text.parse::<f64>() {
-                num.to_chinese(
-                    ChineseVariant::Traditional,
-                    ChineseCase::Lower,
-                    ChineseCountMethod::Low,
-                )
-                .map_or(text.to_owned(), |i| i)
-            } else if let Ok(num) = text.parse::<i64>() {
-                num.to_chinese(
-                    ChineseVariant::Traditional,
-                    ChineseCase::Lower,
-                    ChineseCountMethod::Low,
-                )
-                .map_or(text.to_owned(), |i| i)
+/// 被转换的数字在转换后和转换前的字节范围
+type Span = (Range<usize>, Range<usize>);
+
+/// 把文本中的第一个数字转换为中文，同时返回它的范围
+fn num_repr(regex: &Regex, text: &str) -> (String, Option<Span>) {
+    let Some(m) = regex.find(text) else {
+        return (text.to_owned(), None);
+    };
+    let number = m.as_str();
+    let chinese = if let Ok(num) = number.parse::<f64>() {
+        num.to_chinese(
+            ChineseVariant::Traditional,
+            ChineseCase::Lower,
+            ChineseCountMethod::Low,
+        )
+        .map_or(number.to_owned(), |i| i)
+    } else if let Ok(num) = number.parse::<i64>() {
+        num.to_chinese(
+            ChineseVariant::Traditional,
+            ChineseCase::Lower,
+            ChineseCountMethod::Low,
+        )
+        .map_or(number.to_owned(), |i| i)
+    } else {
+        number.to_owned()
+    };
+    let span = (m.start()..m.start() + chinese.len(), m.range());
+    let result = [&text[..m.start()], &chinese, &text[m.end()..]].concat();
+    (result, Some(span))
+}
+
+/// 把数字转换后的文本中的字节范围映射回转换前，落在转换的数字中时扩展为整个数字
+fn to_source(span: &Option<Span>, range: Range<usize>) -> Range<usize> {
+    let map = |p: usize, end: bool| match span {
+        Some((converted, source)) if p > converted.start && p < converted.end => {
+            if end {
+                source.end
             } else {
-                text.to_owned()
+                source.start
+            }
+        }
+        Some((converted, source)) if p >= converted.end => p - converted.end + source.end,
+        _ => p,
+    };
+    map(range.start, false)..map(range.end, true)
+}
+
+/// 获取单词前后相邻的单词，中间只能隔着空白，遇到标点则认为上下文中断
+fn neighbours<'a>(tokens: &[&'a str], n: usize) -> (Option<&'a str>, Option<&'a str>) {
+    let is_word = |t: &&str| t.chars().next().is_some_and(|c| c.is_alphanumeric());
+    let is_gap = |t: &str| t.chars().all(char::is_whitespace);
+
+    let prev = match n {
+        0 => None,
+        1 => Some(tokens[0]),
+        _ if is_gap(tokens[n - 1]) => Some(tokens[n - 2]),
+        _ => Some(tokens[n - 1]),
+    };
+    let next = match tokens.get(n + 1) {
+        Some(t) if is_gap(t) => tokens.get(n + 2).copied(),
+        t => t.copied(),
+    };
+    (prev.filter(is_word), next.filter(is_word))
+}
+
+/// 音素化结果中一段文本的类别
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Chinese,
+    English,
+    Punctuation,
+}
+
+/// 中文的变调规则
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sandhi {
+    /// “不”的变调，例如不怕
+    Bu,
+    /// “一”的变调，例如一个
+    Yi,
+    /// 轻声，例如桌子
+    Neutral,
+    /// 三声连读，例如你好
+    Third,
+    /// 儿化
+    Erhua,
+}
+
+/// 一个单词或一段标点的转换结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// 在输入文本中的字节范围
+    pub range: Range<usize>,
+    pub language: Language,
+    /// 转换的文本，数字已经被转换为中文
+    pub word: String,
+    /// jieba标注的词性，只有1.1模型的中文有
+    pub tag: Option<String>,
+    /// 中文为每个字的带调拼音，英语为cmudict中的ARPAbet音素，没有时为空
+    pub reading: Vec<String>,
+    pub phonemes: String,
+    /// 改变了读音的变调规则
+    pub sandhi: Vec<Sandhi>,
+}
+
+impl Segment {
+    fn new(range: Range<usize>, language: Language, word: &str, phonemes: String) -> Self {
+        Self {
+            range,
+            language,
+            word: word.to_owned(),
+            tag: None,
+            reading: Vec::new(),
+            phonemes,
+            sandhi: Vec::new(),
+        }
+    }
+}
+
+/// 带有单词范围的音素化结果，参见[`G2p::phonemize`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PhonemizedText {
+    /// 完整的音素，与[`G2p::g2p`]的结果相同
+    pub phonemes: String,
+    /// 按输入顺序排列的各段，空白不单独成段
+    pub segments: Vec<Segment>,
+}
+
+/// 内联发音标注中括号里的内容
+#[derive(Debug, PartialEq)]
+enum Markup {
+    /// `/音素/`
+    Phonemes(String),
+    /// 带数字声调的拼音
+    Pinyin(Vec<String>),
+    /// 重音的调整，范围为-2到2
+    Stress(i8),
+}
+
+impl Markup {
+    fn parse(markup: &str) -> Option<Self> {
+        let markup = markup.trim();
+        if let Some(phonemes) = markup.strip_prefix('/').and_then(|i| i.strip_suffix('/'))
+            && !phonemes.is_empty()
+        {
+            return Some(Self::Phonemes(phonemes.to_owned()));
+        }
+        if markup.starts_with(['+', '-'])
+            && let Ok(level @ (-2..=-1 | 1..=2)) = markup.parse()
+        {
+            return Some(Self::Stress(level));
+        }
+
+        let pinyins = markup
+            .split_whitespace()
+            .map(|i| i.replace('v', "ü"))
+            .collect::<Vec<_>>();
+        let is_pinyin = |p: &String| {
+            p.strip_suffix(['1', '2', '3', '4', '5']).is_some_and(|i| {
+                !i.is_empty() && i.chars().all(|c| c.is_ascii_lowercase() || c == 'ü')
+            })
+        };
+        (!pinyins.is_empty() && pinyins.iter().all(is_pinyin)).then_some(Self::Pinyin(pinyins))
+    }
+}
+
+/// 调整每个单词的重音
+///
+/// 加强时次重音变为主重音，没有重音的单词加上次重音（`+1`）或主重音（`+2`）；减弱时主重音变为次重音（`-1`）或去掉所有重音（`-2`）。
+fn apply_stress(phonemes: &str, level: i8) -> String {
+    phonemes
+        .split(' ')
+        .map(|word| {
+            let primary = word.contains('ˈ');
+            let secondary = word.contains('ˌ');
+            if !word.chars().any(char::is_alphabetic) {
+                return word.to_owned();
+            }
+            match level {
+                ..=-2 => word.replace(['ˈ', 'ˌ'], ""),
+                -1 => word.replace('ˈ', "ˌ"),
+                1.. if primary => word.to_owned(),
+                1.. if secondary => word.replacen('ˌ', "ˈ", 1),
+                1 => format!("ˌ{}", word),
+                _ => format!("ˈ{}", word),
             }
         })
-        .to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
-pub fn g2p(text: &str, use_v11: bool) -> Result<String, G2PError> {
-    let text = num_repr(&text)?;
-    let sentence_pattern = Regex::new(
-        r#"([\u4E00-\u9FFF]+)|([，。：·？、！《》（）【】〖〗〔〕“”‘’〈〉…—　]+)|([\u0000-\u00FF]+)+"#,
-    )?;
-    let en_word_pattern = Regex::new("\\w+|\\W+")?;
-    let jieba = jieba_rs::Jieba::new();
-    let mut result = String::new();
-    for i in sentence_pattern.captures_iter(&text) {
-        match (i.get(1), i.get(2), i.get(3)) {
-            (Some(text), _, _) => {
-                let text = to_half_shape(text.as_str());
-                if use_v11 {
-                    if !result.is_empty() && !result.ends_with(' ') {
-                        result.push(' ');
-                    }
-                    result.push_str(&v11::g2p(&text, true));
-                    result.push(' ');
-                } else {
-                    for i in jieba.cut(&text, true) {
-                        result.push_str(&word2ipa_zh(i)?);
-                        result.push(' ');
+/// 文本到音素的转换引擎
+///
+/// 持有预编译的正则表达式、分词器和发音词典，创建一次后可以反复使用，避免每次转换都重新构建这些资源。
+/// `KokoroTts`内部持有一个该引擎。
+pub struct G2p {
+    num_pattern: Regex,
+    sentence_pattern: Regex,
+    en_word_pattern: Regex,
+    markup_pattern: Regex,
+    jieba: Jieba,
+    /// 1.1模型的中文分词器，加入了拼音词典中的词语
+    jieba_v11: Jieba,
+    #[cfg(feature = "use-cmudict")]
+    cmudict: Cmudict,
+    #[cfg(feature = "use-cmudict")]
+    cmudict_seed: Option<u64>,
+}
+
+impl G2p {
+    /// 创建转换引擎
+    ///
+    /// 该方法会加载分词器和发音词典，耗时较长，应当只调用一次并复用返回的引擎。
+    pub fn new() -> Result<Self, G2PError> {
+        // 提前初始化全局共享的资源，避免第一次转换时才加载
+        LazyLock::force(&ESPEAK);
+
+        Ok(Self {
+            num_pattern: Regex::new(r#"\d+(\.\d+)?"#)?,
+            sentence_pattern: Regex::new(
+                r#"([\u4E00-\u9FFF]+)|([，。：·？、！《》（）【】〖〗〔〕“”‘’〈〉…—　]+)|([\u0000-\u00FF]+)+"#,
+            )?,
+            en_word_pattern: Regex::new("\\w+|\\W+")?,
+            markup_pattern: Regex::new(r"\[([^\[\]]+)\]\(([^()]*)\)")?,
+            jieba: Jieba::new(),
+            jieba_v11: v11::new_jieba(),
+            #[cfg(feature = "use-cmudict")]
+            cmudict: Cmudict::from_str(include_str!("../dict/cmudict.dict"))?,
+            #[cfg(feature = "use-cmudict")]
+            cmudict_seed: None,
+        })
+    }
+
+    /// 设置cmudict读音选择的随机种子
+    ///
+    /// 当上下文无法确定一个词的读音时，默认总是使用cmudict中的第一个（最常用的）读音。
+    /// 设置种子后，会根据种子和单词确定性地从所有读音中挑选一个，相同的种子在每次运行中都会得到相同的结果。
+    /// 种子只影响当前引擎，不同的引擎可以使用不同的种子。
+    ///
+    /// # 参数
+    ///
+    /// * `seed` - 随机种子，传入`None`恢复默认行为。
+    #[cfg(feature = "use-cmudict")]
+    pub fn with_cmudict_seed(mut self, seed: Option<u64>) -> Self {
+        self.cmudict_seed = seed;
+        self
+    }
+
+    #[cfg(feature = "use-cmudict")]
+    fn word2ipa_en(
+        &self,
+        word: &str,
+        variant: EnglishVariant,
+        prev: Option<&str>,
+        next: Option<&str>,
+    ) -> Result<(String, Vec<String>), G2PError> {
+        use super::arpa_to_ipa;
+
+        // cmudict只收录美式发音，英式发音仍然使用espeak的词典
+        if variant == EnglishVariant::British {
+            return Ok((espeak_word2ipa(word, variant)?, Vec::new()));
+        }
+
+        let lower = word.to_lowercase();
+        let Some(rules) = self.cmudict.get(&lower) else {
+            return Ok((oov_word2ipa(word)?, Vec::new()));
+        };
+        if rules.is_empty() {
+            return Ok((word.to_owned(), Vec::new()));
+        }
+        let mut candidates = rules
+            .iter()
+            .map(|i| {
+                i.pronunciation()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let i = en::choose_pronunciation(&lower, prev, next, &candidates, self.cmudict_seed);
+        let result = candidates[i]
+            .iter()
+            .map(|i| arpa_to_ipa(i).unwrap_or_default())
+            .collect::<String>();
+        Ok((result, candidates.swap_remove(i)))
+    }
+
+    #[cfg(not(feature = "use-cmudict"))]
+    fn word2ipa_en(
+        &self,
+        word: &str,
+        variant: EnglishVariant,
+        _prev: Option<&str>,
+        _next: Option<&str>,
+    ) -> Result<(String, Vec<String>), G2PError> {
+        Ok((espeak_word2ipa(word, variant)?, Vec::new()))
+    }
+
+    /// 把中文文本转换为带数字声调的拼音，已经应用“不”“一”、轻声和三声的变调
+    ///
+    /// 每个汉字对应一个拼音，例如“你好”转换为`["ni2", "hao3"]`，标点和其他文字被忽略。
+    /// `erhua`为`true`时合并儿化音，儿化的“儿”转换为`r`。
+    pub fn hanzi_to_pinyin(&self, text: &str, erhua: bool) -> Vec<String> {
+        v11::phonemize(&self.jieba_v11, text, erhua)
+            .1
+            .into_iter()
+            .flat_map(|i| i.pinyins)
+            .collect()
+    }
+
+    /// 将文本转换为音素
+    ///
+    /// 文本中可以使用内联的发音标注覆盖自动转换的结果：
+    ///
+    /// * `[Kokoro](/kˈOkəɹO/)` - 直接使用斜线之间的音素。
+    /// * `[行](hang2)` - 使用带数字声调的拼音，多个音节用空格分隔，`ü`可以写作`v`。
+    /// * `[word](+1)` - 调整英语单词的重音，`+1`、`+2`加强，`-1`、`-2`减弱，`-2`去掉所有重音。
+    ///
+    /// 无法识别的标注保持原样。
+    ///
+    /// # 参数
+    ///
+    /// * `text` - 要转换的文本，可以是中英文混合的文本。
+    /// * `use_v11` - 是否输出1.1模型使用的音素（中文部分使用注音符号）。
+    /// * `variant` - 英语部分使用的发音变体。
+    pub fn g2p(
+        &self,
+        text: &str,
+        use_v11: bool,
+        variant: EnglishVariant,
+    ) -> Result<String, G2PError> {
+        Ok(self.phonemize(text, use_v11, variant)?.phonemes)
+    }
+
+    /// 将文本转换为音素，同时保留每个单词的来源和转换过程
+    ///
+    /// 参数和支持的发音标注与[`Self::g2p`]相同。结果中的每一段对应输入中的一个单词或一段标点，
+    /// 可以用于对齐、调试和发音编辑。
+    pub fn phonemize(
+        &self,
+        text: &str,
+        use_v11: bool,
+        variant: EnglishVariant,
+    ) -> Result<PhonemizedText, G2PError> {
+        let mut result = PhonemizedText::default();
+        let mut last = 0;
+        for i in self.markup_pattern.captures_iter(text) {
+            let (Some(all), Some(label)) = (i.get(0), i.get(1)) else {
+                continue;
+            };
+            let Some(markup) = Markup::parse(&i[2]) else {
+                continue;
+            };
+            self.convert(
+                &text[last..all.start()],
+                last,
+                use_v11,
+                variant,
+                &mut result,
+            )?;
+            last = all.end();
+
+            let language = if label.as_str().is_ascii() {
+                Language::English
+            } else {
+                Language::Chinese
+            };
+            let phonemes = &mut result.phonemes;
+            match markup {
+                Markup::Phonemes(ipa) => {
+                    phonemes.push_str(&ipa);
+                    result
+                        .segments
+                        .push(Segment::new(all.range(), language, label.as_str(), ipa));
+                }
+                Markup::Pinyin(pinyins) => {
+                    let ipa = if use_v11 {
+                        if !phonemes.is_empty() && !phonemes.ends_with(' ') {
+                            phonemes.push(' ');
+                        }
+                        v11::pinyin_to_phonemes(&pinyins)
+                    } else {
+                        let mut ipa = String::new();
+                        for p in &pinyins {
+                            ipa.push_str(&v10::py2ipa(p)?);
+                        }
+                        ipa
+                    };
+                    phonemes.push_str(&ipa);
+                    phonemes.push(' ');
+                    result.segments.push(Segment {
+                        reading: pinyins,
+                        ..Segment::new(all.range(), Language::Chinese, label.as_str(), ipa)
+                    });
+                }
+                Markup::Stress(level) => {
+                    let mut stressed = PhonemizedText::default();
+                    self.convert(
+                        label.as_str(),
+                        label.start(),
+                        use_v11,
+                        variant,
+                        &mut stressed,
+                    )?;
+                    if label.as_str().is_ascii() {
+                        stressed.phonemes = apply_stress(&stressed.phonemes, level);
+                        for i in &mut stressed.segments {
+                            i.phonemes = apply_stress(&i.phonemes, level);
+                        }
                     }
+                    phonemes.push_str(&stressed.phonemes);
+                    result.segments.extend(stressed.segments);
                 }
             }
-            (_, Some(text), _) => {
-                let text = to_half_shape(text.as_str());
-                result = result.trim_end().to_string();
-                result.push_str(&text);
-                result.push(' ');
-            }
-            (_, _, Some(text)) => {
-                for i in en_word_pattern.captures_iter(text.as_str()) {
-                    let c = (&i[0]).chars().nth(0).unwrap_or_default();
-                    if c == '\''
-                        || c == '_'
-                        || c == '-'
-                        || c <= 'z' && c >= 'a'
-                        || c <= 'Z' && c >= 'A'
-                    {
-                        let i = &i[0];
-                        if result
-                            .trim_end()
-                            .ends_with(|c| c == '.' || c == ',' || c == '!' || c == '?')
-                            && !result.ends_with(' ')
-                        {
+        }
+        self.convert(&text[last..], last, use_v11, variant, &mut result)?;
+
+        result.phonemes = result.phonemes.trim().to_string();
+        Ok(result)
+    }
+
+    /// 转换不含发音标注的文本，结果追加到`out`中
+    ///
+    /// `offset`是`text`在整个输入中的字节位置。
+    fn convert(
+        &self,
+        text: &str,
+        offset: usize,
+        use_v11: bool,
+        variant: EnglishVariant,
+        out: &mut PhonemizedText,
+    ) -> Result<(), G2PError> {
+        let (text, span) = num_repr(&self.num_pattern, text);
+        let source = |range: Range<usize>| {
+            let range = to_source(&span, range);
+            range.start + offset..range.end + offset
+        };
+        let result = &mut out.phonemes;
+        for i in self.sentence_pattern.captures_iter(&text) {
+            match (i.get(1), i.get(2), i.get(3)) {
+                (Some(m), _, _) => {
+                    let text = to_half_shape(m.as_str());
+                    if use_v11 {
+                        if !result.is_empty() && !result.ends_with(' ') {
                             result.push(' ');
                         }
-                        result.push_str(&word2ipa_en(i)?);
-                    } else if c == ' ' && result.ends_with(' ') {
-                        result.push_str((&i[0]).trim_start());
+                        let (phonemes, words) = v11::phonemize(&self.jieba_v11, &text, true);
+                        result.push_str(&phonemes);
+                        result.push(' ');
+                        for w in words {
+                            let range = m.start() + w.range.start..m.start() + w.range.end;
+                            out.segments.push(Segment {
+                                tag: Some(w.tag),
+                                reading: w.pinyins,
+                                sandhi: w.sandhi,
+                                ..Segment::new(
+                                    source(range),
+                                    Language::Chinese,
+                                    &w.word,
+                                    w.phonemes,
+                                )
+                            });
+                        }
                     } else {
-                        result.push_str(&i[0]);
+                        let mut start = m.start();
+                        for i in self.jieba.cut(&text, true) {
+                            let (phonemes, pinyins) = word2ipa_zh(i)?;
+                            result.push_str(&phonemes);
+                            result.push(' ');
+                            let range = source(start..start + i.len());
+                            start += i.len();
+                            if !i.trim().is_empty() {
+                                out.segments.push(Segment {
+                                    reading: pinyins,
+                                    ..Segment::new(range, Language::Chinese, i, phonemes)
+                                });
+                            }
+                        }
                     }
                 }
-            }
-            _ => (),
-        };
+                (_, Some(m), _) => {
+                    let text = to_half_shape(m.as_str());
+                    *result = result.trim_end().to_string();
+                    result.push_str(&text);
+                    result.push(' ');
+                    out.segments.push(Segment::new(
+                        source(m.range()),
+                        Language::Punctuation,
+                        m.as_str(),
+                        text,
+                    ));
+                }
+                (_, _, Some(m)) => {
+                    let tokens = self
+                        .en_word_pattern
+                        .find_iter(m.as_str())
+                        .map(|i| i.as_str())
+                        .collect::<Vec<_>>();
+                    let mut start = m.start();
+                    for (n, i) in tokens.iter().enumerate() {
+                        let token_start = start;
+                        start += i.len();
+                        let c = i.chars().nth(0).unwrap_or_default();
+                        if c == '\''
+                            || c == '_'
+                            || c == '-'
+                            || c <= 'z' && c >= 'a'
+                            || c <= 'Z' && c >= 'A'
+                        {
+                            if result
+                                .trim_end()
+                                .ends_with(|c| c == '.' || c == ',' || c == '!' || c == '?')
+                                && !result.ends_with(' ')
+                            {
+                                result.push(' ');
+                            }
+                            let (prev, next) = neighbours(&tokens, n);
+                            let (phonemes, arpabet) = self.word2ipa_en(i, variant, prev, next)?;
+                            result.push_str(&phonemes);
+                            out.segments.push(Segment {
+                                reading: arpabet,
+                                ..Segment::new(
+                                    source(token_start..start),
+                                    Language::English,
+                                    i,
+                                    phonemes,
+                                )
+                            });
+                            continue;
+                        } else if c == ' ' && result.ends_with(' ') {
+                            result.push_str(i.trim_start());
+                        } else {
+                            result.push_str(i);
+                        }
+                        let punctuation = i.trim();
+                        if !punctuation.is_empty() {
+                            let lead = token_start + i.len() - i.trim_start().len();
+                            out.segments.push(Segment::new(
+                                source(lead..lead + punctuation.len()),
+                                Language::Punctuation,
+                                punctuation,
+                                punctuation.to_owned(),
+                            ));
+                        }
+                    }
+                }
+                _ => (),
+            };
+        }
+
+        Ok(())
     }
+}
 
-    Ok(result.trim().to_string())
+/// 将文本转换为音素
+///
+/// 该函数使用一个全局共享的`G2p`引擎，第一次调用时会创建该引擎。
+/// 需要控制引擎生命周期时，请直接使用`G2p`。英语部分使用美式发音，需要其他发音变体时请使用[`g2p_with_variant`]。
+pub fn g2p(text: &str, use_v11: bool) -> Result<String, G2PError> {
+    g2p_with_variant(text, use_v11, EnglishVariant::American)
+}
+
+/// 将文本转换为音素，英语部分使用指定的发音变体
+///
+/// 与[`g2p`]共享同一个全局引擎。
+pub fn g2p_with_variant(
+    text: &str,
+    use_v11: bool,
+    variant: EnglishVariant,
+) -> Result<String, G2PError> {
+    engine()?.g2p(text, use_v11, variant)
+}
+
+/// 将文本转换为带有单词范围的音素，参见[`G2p::phonemize`]
+///
+/// 与[`g2p`]共享同一个全局引擎。
+pub fn phonemize(
+    text: &str,
+    use_v11: bool,
+    variant: EnglishVariant,
+) -> Result<PhonemizedText, G2PError> {
+    engine()?.phonemize(text, use_v11, variant)
+}
+
+/// 把中文文本转换为带数字声调的拼音，参见[`G2p::hanzi_to_pinyin`]
+///
+/// 与[`g2p`]共享同一个全局引擎。
+pub fn hanzi_to_pinyin(text: &str, erhua: bool) -> Result<Vec<String>, G2PError> {
+    Ok(engine()?.hanzi_to_pinyin(text, erhua))
+}
+
+/// 把中文文本转换为注音符号，每个汉字对应一项，参见[`G2p::hanzi_to_pinyin`]
+pub fn hanzi_to_zhuyin(text: &str, erhua: bool) -> Result<Vec<String>, G2PError> {
+    hanzi_to_pinyin(text, erhua)?
+        .iter()
+        .map(|i| pinyin_to_zhuyin(i))
+        .collect()
+}
+
+/// 把带数字声调的拼音转换为注音符号，例如`hang2`转换为`ㄏㄤˊ`，轻声的`˙`在音节前面
+pub fn pinyin_to_zhuyin(pinyin: &str) -> Result<String, G2PError> {
+    Ok(v11::pinyin_to_zhuyin(pinyin)?)
+}
+
+/// 把一串带数字声调的拼音转换为国际音标，音节之间以空格分隔
+///
+/// 每个音节取[`pinyin_to_ipa`]的第一种读法，声调为五度标记，儿化的`r`转换为`ɚ`。
+pub fn pinyins_to_ipa<S: AsRef<str>>(pinyins: &[S]) -> Result<String, G2PError> {
+    let mut syllables = Vec::with_capacity(pinyins.len());
+    for p in pinyins.iter().map(AsRef::as_ref) {
+        if p == "r" {
+            syllables.push("ɚ".to_owned());
+            continue;
+        }
+        let ipa = pinyin_to_ipa(p)?;
+        syllables.push(ipa.first().ok_or(G2PError::EnptyData)?.concat());
+    }
+    Ok(syllables.join(" "))
+}
+
+fn engine() -> Result<&'static G2p, G2PError> {
+    static ENGINE: OnceLock<G2p> = OnceLock::new();
+    match ENGINE.get() {
+        Some(engine) => Ok(engine),
+        None => {
+            let engine = G2p::new()?;
+            Ok(ENGINE.get_or_init(|| engine))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -276,17 +831,169 @@ mod tests {
     #[cfg(not(feature = "use-cmudict"))]
     #[test]
     fn test_word2ipa_en() -> Result<(), super::G2PError> {
-        use super::word2ipa_en;
+        use super::{EnglishVariant::British, G2p};
+        let g2p = G2p::new()?;
 
         // println!("{:?}", espeak_rs::text_to_phonemes("days", "en", None, true, false));
-        assert_eq!("kjˌuːkjˈuː", word2ipa_en("qq")?);
-        assert_eq!("həlˈəʊ", word2ipa_en("hello")?);
-        assert_eq!("wˈɜːld", word2ipa_en("world")?);
-        assert_eq!("ˈapəl", word2ipa_en("apple")?);
-        assert_eq!("tʃˈɪldɹɛn", word2ipa_en("children")?);
-        assert_eq!("ˈaʊə", word2ipa_en("hour")?);
-        assert_eq!("dˈeɪz", word2ipa_en("days")?);
+        assert_eq!("kjˌuːkjˈuː", g2p.word2ipa_en("qq", British, None, None)?.0);
+        assert_eq!("həlˈəʊ", g2p.word2ipa_en("hello", British, None, None)?.0);
+        assert_eq!("wˈɜːld", g2p.word2ipa_en("world", British, None, None)?.0);
+        assert_eq!("ˈapəl", g2p.word2ipa_en("apple", British, None, None)?.0);
+        assert_eq!(
+            "tʃˈɪldɹɛn",
+            g2p.word2ipa_en("children", British, None, None)?.0
+        );
+        assert_eq!("ˈaʊə", g2p.word2ipa_en("hour", British, None, None)?.0);
+        assert_eq!("dˈeɪz", g2p.word2ipa_en("days", British, None, None)?.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_espeak_word2ipa_american() -> Result<(), super::G2PError> {
+        use super::{EnglishVariant::American, espeak_translate, espeak_word2ipa};
+
+        assert_eq!("həlˈoʊ", espeak_word2ipa("hello", American)?);
+        assert_eq!("wˈɜɹld", espeak_word2ipa("world", American)?);
+        assert_eq!("ˈæpəl", espeak_word2ipa("apple", American)?);
+        assert_eq!("ˈaʊəɹ", espeak_word2ipa("hour", American)?);
+        assert_eq!("kˈɑɹ", espeak_word2ipa("car", American)?);
+        assert_eq!("lˈɑt", espeak_word2ipa("lot", American)?);
+        assert_eq!("dˈeɪz", espeak_word2ipa("days", American)?);
+        // 音节数和重音相关的规则只在未登录词的回退路径上生效
+        assert_eq!("hˈæpi", espeak_translate("happy", American, true)?);
+        // 后面的连接r已经带有r音色，不再重复
+        assert_eq!("kˈɑkɔɹˌoʊ", espeak_translate("kokoro", American, true)?);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "use-cmudict")]
+    #[test]
+    fn test_oov_word2ipa() -> Result<(), super::G2PError> {
+        use super::oov_word2ipa;
+
+        assert_eq!("blˈɔɹft", oov_word2ipa("blorft")?);
+        assert_eq!("zˈɛlɛnskˌi", oov_word2ipa("Zelensky")?);
+        assert_eq!("ˈɛnvˈidˈiˈA", oov_word2ipa("NVDA")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_neighbours() {
+        use super::neighbours;
+
+        let tokens = ["I", " ", "read", " ", "it", ", ", "then"];
+        assert_eq!((Some("I"), Some("it")), neighbours(&tokens, 2));
+        assert_eq!((Some("read"), None), neighbours(&tokens, 4));
+        assert_eq!((None, None), neighbours(&tokens, 6));
+    }
+
+    #[test]
+    fn test_markup() {
+        use super::{Markup, apply_stress};
+
+        assert_eq!(
+            Some(Markup::Phonemes("kˈOkəɹO".to_owned())),
+            Markup::parse("/kˈOkəɹO/")
+        );
+        assert_eq!(
+            Some(Markup::Pinyin(vec!["hang2".to_owned(), "lü4".to_owned()])),
+            Markup::parse("hang2 lv4")
+        );
+        assert_eq!(Some(Markup::Stress(-1)), Markup::parse("-1"));
+        assert_eq!(None, Markup::parse("3"));
+        assert_eq!(None, Markup::parse("hang"));
+        assert_eq!(None, Markup::parse("https://example.com"));
+
+        assert_eq!("ˈɪt ˌɪz", apply_stress("ˌɪt ɪz", 1));
+        assert_eq!("ˈɪt ˈɪz", apply_stress("ˌɪt ɪz", 2));
+        assert_eq!("ˌhəlˌO", apply_stress("ˌhəlˈO", -1));
+        assert_eq!("həlO ,", apply_stress("ˌhəlˈO ,", -2));
+    }
+
+    #[test]
+    fn test_g2p_markup() -> Result<(), super::G2PError> {
+        use super::{EnglishVariant::American, G2p};
+
+        let g2p = G2p::new()?;
+        assert_eq!(
+            "kˈOkəɹO ㄏㄤ2",
+            g2p.g2p("[Kokoro](/kˈOkəɹO/) [行](hang2)", true, American)?
+        );
+        assert!(g2p.g2p("[a](b)", true, American)?.starts_with('['));
+        Ok(())
+    }
+
+    #[test]
+    fn test_phonemize() -> Result<(), super::G2PError> {
+        use super::{EnglishVariant::American, G2p, Language, to_source};
+
+        let text = "Hello, [Kokoro](/kˈOkəɹO/)!";
+        let phonemized = G2p::new()?.phonemize(text, true, American)?;
+        assert_eq!(
+            vec![
+                ("Hello", Language::English),
+                (",", Language::Punctuation),
+                ("[Kokoro](/kˈOkəɹO/)", Language::English),
+                ("!", Language::Punctuation),
+            ],
+            phonemized
+                .segments
+                .iter()
+                .map(|i| (&text[i.range.clone()], i.language))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("kˈOkəɹO", phonemized.segments[2].phonemes);
+
+        // “12”转换为“十二”后，范围仍然对应原文
+        let span = Some((2..8, 2..4));
+        assert_eq!(0..2, to_source(&span, 0..2));
+        assert_eq!(2..4, to_source(&span, 2..5));
+        assert_eq!(4..7, to_source(&span, 8..11));
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_reuse() -> Result<(), super::G2PError> {
+        use super::{EnglishVariant::American, G2p, g2p, g2p_with_variant};
+
+        // 复用的引擎、新建的引擎和全局引擎的结果相同
+        let engine = G2p::new()?;
+        let text = "The meeting starts at 10 o'clock.";
+        let expected = engine.g2p(text, false, American)?;
+        for _ in 0..3 {
+            assert_eq!(expected, engine.g2p(text, false, American)?);
+        }
+        assert_eq!(expected, G2p::new()?.g2p(text, false, American)?);
+        assert_eq!(expected, g2p(text, false)?);
+        assert_eq!(expected, g2p_with_variant(text, false, American)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinyins_to_ipa() -> Result<(), super::G2PError> {
+        use super::{pinyin_to_zhuyin, pinyins_to_ipa};
+
+        assert_eq!("ni˧˥ xau˧˩˧", pinyins_to_ipa(&["ni2", "hao3"])?);
+        assert_eq!("ㄋㄧˇ", pinyin_to_zhuyin("ni3")?);
+        assert_eq!("˙ㄋㄧ", pinyin_to_zhuyin("ni")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hanzi_to_pinyin() -> Result<(), super::G2PError> {
+        use super::{hanzi_to_pinyin, hanzi_to_zhuyin};
+
+        assert_eq!(vec!["ni2", "hao3"], hanzi_to_pinyin("你好", false)?);
+        assert_eq!(vec!["yi2", "ge5"], hanzi_to_pinyin("一个", false)?);
+        assert_eq!(vec!["bu2", "pa4"], hanzi_to_pinyin("不怕", false)?);
+        assert_eq!(vec!["hua1", "er2"], hanzi_to_pinyin("花儿", false)?);
+        assert_eq!(vec!["hua1", "r"], hanzi_to_pinyin("花儿", true)?);
 
+        assert_eq!(vec!["ㄋㄧˊ", "ㄏㄠˇ"], hanzi_to_zhuyin("你好", false)?);
+        assert_eq!(vec!["ㄏㄨㄚ", "ㄦ"], hanzi_to_zhuyin("花儿", true)?);
         Ok(())
     }
 
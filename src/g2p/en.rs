@@ -0,0 +1,258 @@
+//! 英语多音词（heteronym）消歧
+//! cmudict对同一个词可能收录多个读音，这里根据上下文推测词性，确定性地选出其中一个。
+
+/// 限定词之后通常是名词
+const DETERMINERS: [&str; 20] = [
+    "a", "an", "the", "this", "that", "these", "those", "my", "your", "his", "her", "its", "our",
+    "their", "every", "each", "no", "some", "any", "another",
+];
+/// 不定式、情态动词和主语代词之后通常是动词原形
+const VERB_MARKERS: [&str; 24] = [
+    "to", "will", "would", "can", "could", "shall", "should", "may", "might", "must", "do", "does",
+    "did", "don't", "doesn't", "didn't", "won't", "can't", "cannot", "let's", "i", "you", "we",
+    "they",
+];
+/// 完成时、被动语态和第三人称单数主语之后通常是过去式或过去分词
+const PAST_MARKERS: [&str; 14] = [
+    "have", "has", "had", "having", "was", "were", "been", "be", "is", "are", "he", "she", "it",
+    "already",
+];
+/// 程度副词之后通常是形容词
+const ADJECTIVE_MARKERS: [&str; 7] = ["very", "so", "too", "more", "most", "less", "least"];
+/// 紧跟宾语的词通常是及物动词
+const OBJECTS: [&str; 15] = [
+    "a", "an", "the", "my", "your", "his", "her", "its", "our", "their", "it", "them", "me", "us",
+    "him",
+];
+
+/// 名词和动词重音位置不同的多音词，名词（或形容词）重音在第一个音节，动词重音在后面的音节
+const STRESS_SHIFT: [&str; 45] = [
+    "address",
+    "combat",
+    "compound",
+    "compress",
+    "conduct",
+    "conflict",
+    "console",
+    "contest",
+    "contract",
+    "convert",
+    "convict",
+    "decrease",
+    "desert",
+    "digest",
+    "discount",
+    "escort",
+    "export",
+    "extract",
+    "frequent",
+    "import",
+    "increase",
+    "insult",
+    "object",
+    "perfect",
+    "permit",
+    "present",
+    "produce",
+    "progress",
+    "project",
+    "protest",
+    "rebel",
+    "record",
+    "refund",
+    "refuse",
+    "reject",
+    "segment",
+    "subject",
+    "survey",
+    "suspect",
+    "torment",
+    "transfer",
+    "transport",
+    "upgrade",
+    "upset",
+    "update",
+];
+
+/// 读音随词性变化的多音词：(单词, 名词, 动词原形, 过去式, 形容词)
+const HETERONYMS: [(&str, &str, &str, &str, &str); 10] = [
+    ("read", "R IY1 D", "R IY1 D", "R EH1 D", "R EH1 D"),
+    ("lead", "L EH1 D", "L IY1 D", "L IY1 D", "L IY1 D"),
+    ("live", "L AY1 V", "L IH1 V", "L AY1 V", "L AY1 V"),
+    ("wind", "W IH1 N D", "W AY1 N D", "W AY1 N D", "W IH1 N D"),
+    ("tear", "T IH1 R", "T EH1 R", "T EH1 R", "T IH1 R"),
+    ("close", "K L OW1 Z", "K L OW1 Z", "K L OW1 S", "K L OW1 S"),
+    ("use", "Y UW1 S", "Y UW1 Z", "Y UW1 Z", "Y UW1 Z"),
+    ("wound", "W UW1 N D", "W UW1 N D", "W AW1 N D", "W UW1 N D"),
+    ("bow", "B OW1", "B AW1", "B AW1", "B OW1"),
+    (
+        "minute",
+        "M IH1 N AH0 T",
+        "M IH1 N AH0 T",
+        "M IH1 N AH0 T",
+        "M AY0 N UW1 T",
+    ),
+];
+
+/// 根据上下文推测的词性
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pos {
+    Noun,
+    Verb,
+    Past,
+    Adjective,
+    Unknown,
+}
+
+fn guess_pos(prev: Option<&str>, next: Option<&str>) -> Pos {
+    let prev = prev.map(str::to_lowercase);
+    let next = next.map(str::to_lowercase);
+    match (prev.as_deref(), next.as_deref()) {
+        (Some(p), _) if DETERMINERS.contains(&p) => Pos::Noun,
+        (Some(p), _) if VERB_MARKERS.contains(&p) => Pos::Verb,
+        (Some(p), _) if PAST_MARKERS.contains(&p) => Pos::Past,
+        (Some(p), _) if ADJECTIVE_MARKERS.contains(&p) => Pos::Adjective,
+        (_, Some("of")) => Pos::Noun,
+        (_, Some(n)) if OBJECTS.contains(&n) => Pos::Verb,
+        _ => Pos::Unknown,
+    }
+}
+
+fn fnv1a(seed: u64, word: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for b in seed.to_le_bytes().iter().chain(word.as_bytes()) {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// 第一个元音是否带主重音
+fn is_initial_stress(phones: &[String]) -> bool {
+    phones
+        .iter()
+        .find(|p| p.ends_with(|c: char| c.is_ascii_digit()))
+        .is_some_and(|p| p.ends_with('1'))
+}
+
+/// 从cmudict的多个读音中选出一个
+///
+/// * `word`: 小写的单词
+/// * `prev`, `next`: 前后相邻的单词
+/// * `candidates`: cmudict中的全部读音（ARPAbet音素序列），不能为空
+/// * `seed`: 上下文无法确定读音时用于挑选读音的种子，为`None`时使用第一个读音
+///
+/// Return: 选中的读音的下标
+pub(super) fn choose_pronunciation(
+    word: &str,
+    prev: Option<&str>,
+    next: Option<&str>,
+    candidates: &[Vec<String>],
+    seed: Option<u64>,
+) -> usize {
+    if candidates.len() < 2 {
+        return 0;
+    }
+    let pos = guess_pos(prev, next);
+    let find = |arpa: &str| {
+        candidates
+            .iter()
+            .position(|c| c.iter().map(String::as_str).eq(arpa.split(' ')))
+    };
+
+    // the在元音前读作/ði/
+    if word == "the" {
+        let before_vowel = next.is_some_and(|n| n.starts_with(|c: char| "aeiouAEIOU".contains(c)));
+        return find(if before_vowel { "DH IY0" } else { "DH AH0" }).unwrap_or(0);
+    }
+
+    if pos != Pos::Unknown {
+        if let Some(&(_, noun, verb, past, adjective)) = HETERONYMS.iter().find(|i| i.0 == word) {
+            let arpa = match pos {
+                Pos::Noun => noun,
+                Pos::Verb => verb,
+                Pos::Past => past,
+                _ => adjective,
+            };
+            if let Some(i) = find(arpa) {
+                return i;
+            }
+        }
+        if STRESS_SHIFT.contains(&word) {
+            let initial = matches!(pos, Pos::Noun | Pos::Adjective);
+            if let Some(i) = candidates
+                .iter()
+                .position(|c| is_initial_stress(c) == initial)
+            {
+                return i;
+            }
+        }
+    }
+
+    seed.map_or(0, |seed| {
+        (fnv1a(seed, word) % candidates.len() as u64) as usize
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(arpa: &[&str]) -> Vec<Vec<String>> {
+        arpa.iter()
+            .map(|i| i.split(' ').map(ToString::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_guess_pos() {
+        assert_eq!(Pos::Noun, guess_pos(Some("the"), None));
+        assert_eq!(Pos::Verb, guess_pos(Some("to"), Some("music")));
+        assert_eq!(Pos::Past, guess_pos(Some("has"), None));
+        assert_eq!(Pos::Verb, guess_pos(None, Some("the")));
+        assert_eq!(Pos::Unknown, guess_pos(None, None));
+    }
+
+    #[test]
+    fn test_choose_pronunciation() {
+        let read = candidates(&["R EH1 D", "R IY1 D"]);
+        assert_eq!(
+            1,
+            choose_pronunciation("read", Some("to"), None, &read, None)
+        );
+        assert_eq!(
+            0,
+            choose_pronunciation("read", Some("had"), None, &read, None)
+        );
+
+        let record = candidates(&["R AH0 K AO1 R D", "R EH1 K ER0 D", "R IH0 K AO1 R D"]);
+        assert_eq!(
+            1,
+            choose_pronunciation("record", Some("a"), None, &record, None)
+        );
+        assert_eq!(
+            0,
+            choose_pronunciation("record", Some("will"), None, &record, None)
+        );
+
+        let the = candidates(&["DH AH0", "DH AH1", "DH IY0"]);
+        assert_eq!(
+            2,
+            choose_pronunciation("the", None, Some("apple"), &the, None)
+        );
+        assert_eq!(
+            0,
+            choose_pronunciation("the", None, Some("pear"), &the, None)
+        );
+
+        for _ in 0..3 {
+            assert_eq!(0, choose_pronunciation("read", None, None, &read, None));
+        }
+        // 相同的种子总是选出相同的读音
+        let seeded = choose_pronunciation("record", None, None, &record, Some(7));
+        assert_eq!(
+            seeded,
+            choose_pronunciation("record", None, None, &record, Some(7))
+        );
+    }
+}
@@ -1,13 +1,16 @@
 /// 参考了python的misaki库的zh_frontend.py。
 use {
-    crate::{split_initial, split_tone},
+    super::Sandhi,
+    crate::{PinyinError, split_initial, split_tone},
     chinese_number::{ChineseCountMethod, ChineseToNumber},
     jieba_rs::Jieba,
     pinyin::ToPinyin,
-    std::{collections::HashMap, sync::LazyLock},
+    std::{collections::HashMap, ops::Range, sync::LazyLock},
 };
 
 const BU: &str = "不";
+/// 韵母为舌尖元音`-i`的声母
+const APICAL: [&str; 7] = ["z", "c", "s", "zh", "ch", "sh", "r"];
 const YI: &str = "一";
 const X_ENG: [&str; 2] = ["x", "eng"];
 const PUNC: &str = ";: ,.!?—…\"()“”";
@@ -162,14 +165,6 @@ static PHRASES_DICT: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
     map
 });
 
-static JIEBA: LazyLock<Jieba> = LazyLock::new(|| {
-    let mut jieba = Jieba::new();
-    for k in PHRASES_DICT.keys() {
-        jieba.add_word(k, None, Some("x"));
-    }
-
-    jieba
-});
 static ZH_MAP: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
     let mut map = HashMap::new();
     map.insert("b", "ㄅ");
@@ -529,8 +524,8 @@ fn split_at_char(s: &str, mid: usize) -> (&str, &str) {
     s.split_at(byte_pos)
 }
 
-fn split_word(word: &str) -> (String, String) {
-    let mut word_list = JIEBA.cut_for_search(word, true);
+fn split_word(jieba: &Jieba, word: &str) -> (String, String) {
+    let mut word_list = jieba.cut_for_search(word, true);
     word_list.sort_by_cached_key(|i| i.chars().count());
     let first_subword = &word_list[0];
     if let Some(0) = word.find(first_subword) {
@@ -547,7 +542,7 @@ fn split_word(word: &str) -> (String, String) {
 /// word: "家里"
 /// pos: "s"
 /// finals: ['ia1', 'i3']
-fn neural_sandhi(word: &str, pos: &str, pinyins: &mut Vec<String>) {
+fn neural_sandhi(jieba: &Jieba, word: &str, pos: &str, pinyins: &mut Vec<String>) {
     if MUST_NOT_NEURAL_TONE_WORDS.contains(&word) {
         return;
     }
@@ -655,7 +650,7 @@ fn neural_sandhi(word: &str, pos: &str, pinyins: &mut Vec<String>) {
         }
     }
 
-    let (left_word, right_word) = split_word(word);
+    let (left_word, right_word) = split_word(jieba, word);
     let (left_pinyins, right_pinyins) = pinyins.split_at_mut(left_word.chars().count());
 
     // conventional neural in Chinese
@@ -679,7 +674,7 @@ fn neural_sandhi(word: &str, pos: &str, pinyins: &mut Vec<String>) {
     }
 }
 
-fn three_sandhi(word: &str, pinyins: &mut Vec<String>) {
+fn three_sandhi(jieba: &Jieba, word: &str, pinyins: &mut Vec<String>) {
     let len = word.chars().count();
     if len == 2 && pinyins.iter().all(|i| i.ends_with("3")) {
         pinyins.first_mut().and_then(|i| {
@@ -687,7 +682,7 @@ fn three_sandhi(word: &str, pinyins: &mut Vec<String>) {
             Some(i.push('2'))
         });
     } else if len == 3 {
-        let (left_word, _) = split_word(word);
+        let (left_word, _) = split_word(jieba, word);
         if pinyins.iter().all(|i| i.ends_with("3")) {
             //  disyllabic + monosyllabic, e.g. 蒙古/包
             if left_word.chars().count() == 2 {
@@ -798,14 +793,28 @@ fn get_pinyin_fine(word: &str) -> Vec<String> {
     pinyin
 }
 
+type ToneRule<'a> = (Sandhi, &'a dyn Fn(&mut Vec<String>));
+
 /// * `word`: 分词
 /// * `pos`: 词性
 /// * `pinyins`: 带调拼音, [pinyin1, ..., pinyinN]
-fn modified_tone(word: &str, pos: &str, pinyins: &mut Vec<String>) {
-    bu_sandhi(word, pinyins);
-    yi_sandhi(word, pinyins);
-    neural_sandhi(word, pos, pinyins);
-    three_sandhi(word, pinyins);
+///
+/// 返回改变了读音的变调规则。
+fn modified_tone(jieba: &Jieba, word: &str, pos: &str, pinyins: &mut Vec<String>) -> Vec<Sandhi> {
+    let rules: [ToneRule; 4] = [
+        (Sandhi::Bu, &|p| bu_sandhi(word, p)),
+        (Sandhi::Yi, &|p| yi_sandhi(word, p)),
+        (Sandhi::Neutral, &|p| neural_sandhi(jieba, word, pos, p)),
+        (Sandhi::Third, &|p| three_sandhi(jieba, word, p)),
+    ];
+    rules
+        .into_iter()
+        .filter_map(|(sandhi, rule)| {
+            let before = pinyins.clone();
+            rule(pinyins);
+            (*pinyins != before).then_some(sandhi)
+        })
+        .collect()
 }
 
 fn merge_erhua(word: &str, pos: &str, pinyins: &mut Vec<String>) {
@@ -852,10 +861,39 @@ fn merge_erhua(word: &str, pos: &str, pinyins: &mut Vec<String>) {
     }
 }
 
+/// 创建分词器，拼音词典中的词语作为整体切分
+pub(super) fn new_jieba() -> Jieba {
+    let mut jieba = Jieba::new();
+    for k in PHRASES_DICT.keys() {
+        jieba.add_word(k, None, Some("x"));
+    }
+
+    jieba
+}
+
+/// 一个分词的转换结果
+pub(super) struct Word {
+    /// 在输入文本中的字节范围
+    pub(super) range: Range<usize>,
+    pub(super) word: String,
+    /// jieba标注的词性
+    pub(super) tag: String,
+    /// 变调后的带调拼音
+    pub(super) pinyins: Vec<String>,
+    pub(super) sandhi: Vec<Sandhi>,
+    pub(super) phonemes: String,
+}
+
 /// Return: string of phonemes.
 /// 'ㄋㄧ2ㄏㄠ3/ㄕ十4ㄐㄝ4'
+#[cfg(test)]
 pub(super) fn g2p(text: &str, with_erhua: bool) -> String {
-    let mut seg_cut = JIEBA
+    phonemize(&new_jieba(), text, with_erhua).0
+}
+
+/// 转换文本，同时返回每个分词的转换结果
+pub(super) fn phonemize(jieba: &Jieba, text: &str, with_erhua: bool) -> (String, Vec<Word>) {
+    let mut seg_cut = jieba
         .tag(text, true)
         .iter()
         .map(|i| (i.word.to_string(), i.tag.to_string()))
@@ -866,14 +904,19 @@ pub(super) fn g2p(text: &str, with_erhua: bool) -> String {
 
     struct MToken {
         tag: String,
-        phonemes: String,
+        word: Word,
         whitespace: String,
     }
 
     // 为了多音词获得更好的效果，这里采用整句预测
     let mut tokens = Vec::with_capacity(seg_cut.len());
+    let mut offset = 0;
     // pypinyin, g2pM
     for (word, pos) in seg_cut.iter() {
+        let start = text[offset..]
+            .find(word.as_str())
+            .map_or(offset, |i| offset + i);
+        offset = start + word.len();
         let tag = if pos == "x"
             && word
                 .chars()
@@ -895,12 +938,19 @@ pub(super) fn g2p(text: &str, with_erhua: bool) -> String {
         let mut tk = MToken {
             tag,
             whitespace: Default::default(),
-            phonemes: Default::default(),
+            word: Word {
+                range: start..offset,
+                word: word.to_owned(),
+                tag: pos.to_owned(),
+                pinyins: Vec::new(),
+                sandhi: Vec::new(),
+                phonemes: Default::default(),
+            },
         };
         if X_ENG.contains(&tk.tag.as_str()) {
             if !word.trim().is_empty() {
                 if tk.tag == "x" && PUNC.contains(word) {
-                    tk.phonemes = word.to_owned();
+                    tk.word.phonemes = word.to_owned();
                 }
                 tokens.push(tk);
             } else if !tokens.is_empty() {
@@ -925,51 +975,134 @@ pub(super) fn g2p(text: &str, with_erhua: bool) -> String {
         // g2p
         let mut pinyins = get_pinyin_fine(word);
         // tone sandhi
-        modified_tone(word, pos, &mut pinyins);
+        tk.word.sandhi = modified_tone(jieba, word, pos, &mut pinyins);
         // er hua
         if with_erhua {
+            let before = pinyins.clone();
             merge_erhua(word, pos, &mut pinyins);
+            if pinyins != before {
+                tk.word.sandhi.push(Sandhi::Erhua);
+            }
+        }
+
+        tk.word.phonemes = pinyin_to_phonemes(&pinyins);
+        tk.word.pinyins = pinyins.iter().map(|i| standard_pinyin(i)).collect();
+        tokens.push(tk);
+    }
+
+    let mut phonemes = String::new();
+    let mut words = Vec::with_capacity(tokens.len());
+    for mut tk in tokens {
+        if tk.word.phonemes.is_empty() {
+            tk.word.phonemes = UNK.to_owned();
+        }
+        phonemes.push_str(&tk.word.phonemes);
+        phonemes.push_str(&tk.whitespace);
+        words.push(tk.word);
+    }
+    (phonemes, words)
+}
+
+/// 把带数字声调的拼音（例如`hang2`）转换为1.1模型的音素
+pub(super) fn pinyin_to_phonemes<S: AsRef<str>>(pinyins: &[S]) -> String {
+    let mut phones = Vec::with_capacity(pinyins.len());
+    for p in pinyins.iter().map(AsRef::as_ref) {
+        // NOTE: post process for pypinyin outputs
+        // we discriminate i, ii and iii
+        let (c, v) = split_initial(p);
+        let mut v = v.to_owned();
+        convert_pinyin(c, &mut v);
+        let (f, t) = split_tone(v.as_str());
+        if !c.is_empty() {
+            phones.push(c.to_owned());
+        }
+        // replace punctuation by ` `
+        if !v.is_empty() {
+            // and v not in rhy_phns:
+            if !PUNC.contains(v.as_str()) {
+                phones.push(f.to_owned());
+                phones.push(t.to_string());
+            } else if v != c {
+                phones.push(v.to_owned());
+            }
         }
+    }
+    let phones = phones.join("_").replace("_eR", "_er").replace('R', "_R");
+    phones
+        .split('_')
+        .map(|c| *ZH_MAP.get(c).unwrap_or(&UNK))
+        .collect::<String>()
+}
+
+/// 还原`get_pinyin_fine`和`merge_erhua`改写的拼音，例如`shiii4`还原为`shi4`，儿化的`erR2`转换为`r`
+fn standard_pinyin(pinyin: &str) -> String {
+    if pinyin.contains('R') {
+        return "r".to_owned();
+    }
+    let (c, v) = split_initial(pinyin);
+    if APICAL.contains(&c) && v.starts_with("ii") {
+        format!("{}i{}", c, v.trim_start_matches('i'))
+    } else {
+        pinyin.to_owned()
+    }
+}
 
-        let mut phones = Vec::with_capacity(pinyins.len());
-        for p in pinyins.iter() {
-            // NOTE: post process for pypinyin outputs
-            // we discriminate i, ii and iii
-            let (c, v) = split_initial(p);
+/// 把带数字声调的拼音（例如`hang2`）转换为注音符号（`ㄏㄤˊ`），没有声调数字时视为轻声
+pub(super) fn pinyin_to_zhuyin(pinyin: &str) -> Result<String, PinyinError> {
+    let invalid = || PinyinError::FinalNotFound(pinyin.to_owned());
+    // 儿化
+    if pinyin == "r" {
+        return Ok("ㄦ".to_owned());
+    }
+    let (syllable, tone) = split_tone(pinyin);
+    let zhuyin = match syllable {
+        // 自成音节的鼻音，例如呣（m2）、嗯（n2、ng2）、噷（hm）、哼（hng）
+        "m" => "ㄇ".to_owned(),
+        "n" => "ㄋ".to_owned(),
+        "ng" => "ㄫ".to_owned(),
+        "hm" => "ㄏㄇ".to_owned(),
+        "hng" => "ㄏㄫ".to_owned(),
+        _ => {
+            let (c, v) = split_initial(syllable);
             let mut v = v.to_owned();
             convert_pinyin(c, &mut v);
-            let (f, t) = split_tone(v.as_str());
-            if !c.is_empty() {
-                phones.push(c.to_owned());
-            }
-            // replace punctuation by ` `
-            if !v.is_empty() {
-                // and v not in rhy_phns:
-                if !PUNC.contains(v.as_str()) {
-                    phones.push(f.to_owned());
-                    phones.push(t.to_string());
-                } else if v != c {
-                    phones.push(v.to_owned());
+
+            let mut zhuyin = ZH_MAP.get(c).map_or_else(String::new, |i| i.to_string());
+            let (medial, rest) = match v.as_str() {
+                // zhi、chi、shi、ri、zi、ci、si只写声母
+                "i" | "ii" | "iii" if APICAL.contains(&c) => ("", ""),
+                "ong" => ("ㄨ", "ng"),
+                "iong" => ("ㄩ", "ng"),
+                v => match v.chars().next() {
+                    Some('i') => ("ㄧ", &v[1..]),
+                    Some('u') => ("ㄨ", &v[1..]),
+                    Some('v') => ("ㄩ", &v[1..]),
+                    _ => ("", v),
+                },
+            };
+            zhuyin.push_str(medial);
+            zhuyin.push_str(match rest {
+                "" if !medial.is_empty() || (APICAL.contains(&c) && !v.is_empty()) => "",
+                "e" if !medial.is_empty() => "ㄝ",
+                "n" if !medial.is_empty() => "ㄣ",
+                "ng" if !medial.is_empty() => "ㄥ",
+                "er" if medial.is_empty() && c.is_empty() => "ㄦ",
+                "a" | "o" | "e" | "ai" | "ei" | "ao" | "ou" | "an" | "en" | "ang" | "eng" => {
+                    ZH_MAP.get(rest).ok_or_else(invalid)?
                 }
-            }
+                _ => return Err(invalid()),
+            });
+            zhuyin
         }
-        let phones = phones.join("_").replace("_eR", "_er").replace('R', "_R");
-        tk.phonemes = phones
-            .split('_')
-            .map(|c| *ZH_MAP.get(c).unwrap_or(&UNK))
-            .collect::<String>();
-        tokens.push(tk);
-    }
+    };
 
-    tokens
-        .iter()
-        .map(|tk| {
-            if tk.phonemes.is_empty() {
-                return UNK.to_owned() + &tk.whitespace;
-            }
-            tk.phonemes.to_owned() + &tk.whitespace
-        })
-        .collect()
+    Ok(match tone {
+        2 => zhuyin + "ˊ",
+        3 => zhuyin + "ˇ",
+        4 => zhuyin + "ˋ",
+        5 => format!("˙{}", zhuyin),
+        _ => zhuyin,
+    })
 }
 
 fn convert_pinyin(initial_part: &str, final_part: &mut String) {
@@ -1170,17 +1303,18 @@ mod tests {
 
     #[test]
     fn test_neural_sandhi() {
+        let jieba = new_jieba();
         let mut pinyin = vec!["yi1".into(), "fu2".into()];
-        neural_sandhi("衣服", "n", &mut pinyin);
+        neural_sandhi(&jieba, "衣服", "n", &mut pinyin);
         assert_eq!(vec!["yi1".to_string(), "fu5".into()], pinyin);
         let mut pinyin = vec!["yi1".into(), "ge4".into()];
-        neural_sandhi("一个", "m", &mut pinyin);
+        neural_sandhi(&jieba, "一个", "m", &mut pinyin);
         assert_eq!(vec!["yi1".to_string(), "ge5".into()], pinyin);
         let mut pinyin = vec!["hu2".into(), "lu2".into()];
-        neural_sandhi("葫芦", "n", &mut pinyin);
+        neural_sandhi(&jieba, "葫芦", "n", &mut pinyin);
         assert_eq!(vec!["hu2".to_string(), "lu5".into()], pinyin);
         let mut pinyin = vec!["jian3".to_string(), "dan1".into(), "de".into()];
-        neural_sandhi("簡單的", "a", &mut pinyin);
+        neural_sandhi(&jieba, "簡單的", "a", &mut pinyin);
         assert_eq!(
             vec!["jian3".to_string(), "dan1".into(), "de5".into()],
             pinyin
@@ -1189,17 +1323,18 @@ mod tests {
 
     #[test]
     fn test_three_sandhi() {
+        let jieba = new_jieba();
         let mut pinyin = vec!["ni3".into(), "hao3".into()];
-        three_sandhi("你好", &mut pinyin);
+        three_sandhi(&jieba, "你好", &mut pinyin);
         assert_eq!(vec!["ni2".to_string(), "hao3".into()], pinyin);
         let mut pinyin = vec!["suo3".into(), "you3".into(), "ren2".into()];
-        three_sandhi("所有人", &mut pinyin);
+        three_sandhi(&jieba, "所有人", &mut pinyin);
         assert_eq!(
             vec!["suo2".to_string(), "you3".into(), "ren2".into()],
             pinyin
         );
         let mut pinyin = vec!["zhu3".into(), "zai3".into()];
-        three_sandhi("主宰", &mut pinyin);
+        three_sandhi(&jieba, "主宰", &mut pinyin);
         assert_eq!(vec!["zhu2".to_string(), "zai3".into()], pinyin);
     }
 
@@ -1221,7 +1356,7 @@ mod tests {
 
     #[test]
     fn test_split_word() {
-        let (left, right) = split_word("你好呀");
+        let (left, right) = split_word(&new_jieba(), "你好呀");
         assert_eq!(left, "你好");
         assert_eq!(right, "呀");
     }
@@ -1229,7 +1364,10 @@ mod tests {
     #[test]
     fn test_modified_tone() {
         let mut pinyin = vec!["kan4".to_string(), "yi1".into(), "kan4".into()];
-        modified_tone("看一看", "v", &mut pinyin);
+        assert_eq!(
+            vec![Sandhi::Yi],
+            modified_tone(&new_jieba(), "看一看", "v", &mut pinyin)
+        );
         assert_eq!(
             vec!["kan4".to_string(), "yi5".into(), "kan4".into()],
             pinyin
@@ -1272,4 +1410,31 @@ mod tests {
         assert_eq!(g2p("借还款", true), "ㄐㄝ4ㄏ万2ㄎ万3");
         assert_eq!(g2p("时间为", true), "ㄕ十2ㄐ言1为2");
     }
+
+    #[test]
+    fn test_pinyin_to_zhuyin() -> Result<(), PinyinError> {
+        let test_cases = [
+            ("hang2", "ㄏㄤˊ"),
+            ("shi4", "ㄕˋ"),
+            ("zii3", "ㄗˇ"),
+            ("yue4", "ㄩㄝˋ"),
+            ("xiong1", "ㄒㄩㄥ"),
+            ("gui4", "ㄍㄨㄟˋ"),
+            ("lv3", "ㄌㄩˇ"),
+            ("er2", "ㄦˊ"),
+            ("men5", "˙ㄇㄣ"),
+            ("m2", "ㄇˊ"),
+            ("n2", "ㄋˊ"),
+            ("ng4", "ㄫˋ"),
+            ("hm", "˙ㄏㄇ"),
+            ("hng1", "ㄏㄫ"),
+        ];
+        for (pinyin, expected) in test_cases {
+            assert_eq!(expected, pinyin_to_zhuyin(pinyin)?, "failed: {}", pinyin);
+        }
+        assert!(pinyin_to_zhuyin("bx1").is_err());
+        assert_eq!("shi4", standard_pinyin("shiii4"));
+        assert_eq!("r", standard_pinyin("erR2"));
+        Ok(())
+    }
 }
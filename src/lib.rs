@@ -1,35 +1,86 @@
+mod audio;
+mod audiobook;
+pub mod blocking;
+mod cache;
+#[cfg(feature = "capi")]
+mod capi;
+mod dialogue;
 mod error;
 mod g2p;
+mod model;
+mod pause;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "server")]
+mod server;
 mod stream;
+mod subtitle;
 mod synthesizer;
 mod tokenizer;
 mod transcription;
 mod voice;
+#[cfg(feature = "wyoming")]
+mod wyoming;
 
+#[cfg(feature = "capi")]
+pub use capi::*;
+#[cfg(feature = "server")]
+pub use server::*;
+#[cfg(feature = "wyoming")]
+pub use wyoming::*;
+pub use {
+    audio::*, audiobook::*, cache::*, dialogue::*, error::*, g2p::*, model::*, pause::*, stream::*,
+    subtitle::*, tokenizer::*, transcription::*, voice::*,
+};
 use {
+    audio::{fit_to_length, from_samples, to_samples},
     bincode::{config::standard, decode_from_slice},
+    cache::{Fnv, fingerprint},
+    model::Model,
     ort::{execution_providers::CUDAExecutionProvider, session::Session},
-    std::{collections::HashMap, path::Path, sync::Arc, time::Duration},
-    tokio::{fs::read, sync::Mutex},
+    std::{
+        collections::HashMap,
+        fs::{metadata, read},
+        path::Path,
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    },
+    synthesizer::{Settings, Synthesized},
 };
-pub use {error::*, g2p::*, stream::*, tokenizer::*, transcription::*, voice::*};
 
 pub struct KokoroTts {
-    model: Arc<Mutex<Session>>,
+    g2p: Arc<G2p>,
+    model: Arc<Model>,
     voices: Arc<HashMap<String, Vec<Vec<Vec<f32>>>>>,
+    settings: Settings,
+    pauses: Option<Pauses>,
 }
 
 impl KokoroTts {
     pub async fn new<P: AsRef<Path>>(model_path: P, voices_path: P) -> Result<Self, KokoroError> {
-        let voices = read(voices_path).await?;
+        let mut hasher = Fnv::default();
+        let meta = metadata(model_path.as_ref())?;
+        hasher.write(&meta.len().to_le_bytes());
+        hasher.write(
+            &meta
+                .modified()?
+                .duration_since(UNIX_EPOCH)?
+                .as_nanos()
+                .to_le_bytes(),
+        );
+        let voices = read(voices_path)?;
+        fingerprint(&mut hasher, &voices);
         let (voices, _) = decode_from_slice(&voices, standard())?;
 
         let model = Session::builder()?
             .with_execution_providers([CUDAExecutionProvider::default().build()])?
             .commit_from_file(model_path)?;
         Ok(Self {
-            model: Arc::new(model.into()),
+            g2p: Arc::new(G2p::new()?),
+            model: Arc::new(Model::new(model)?),
             voices,
+            settings: Settings::new(hasher.finish()),
+            pauses: None,
         })
     }
 
@@ -37,46 +88,451 @@ impl KokoroTts {
     where
         B: AsRef<[u8]>,
     {
+        let mut hasher = Fnv::default();
+        fingerprint(&mut hasher, model.as_ref());
+        fingerprint(&mut hasher, voices.as_ref());
         let (voices, _) = decode_from_slice(voices.as_ref(), standard())?;
 
         let model = Session::builder()?
             .with_execution_providers([CUDAExecutionProvider::default().build()])?
             .commit_from_memory(model.as_ref())?;
         Ok(Self {
-            model: Arc::new(model.into()),
+            g2p: Arc::new(G2p::new()?),
+            model: Arc::new(Model::new(model)?),
             voices,
+            settings: Settings::new(hasher.finish()),
+            pauses: None,
         })
     }
 
+    /// 获取加载的模型版本
+    pub fn model_version(&self) -> ModelVersion {
+        self.model.version
+    }
+
+    /// 获取所有已加载的发音人名称
+    pub fn voice_names(&self) -> impl Iterator<Item = &str> {
+        self.voices.keys().map(String::as_str)
+    }
+
+    /// 使用自定义的音素词表
+    ///
+    /// 默认根据音色的版本使用内置的v1.0或v1.1词表，词表不同的新模型可以通过[`Vocabulary::from_config_file`]加载其config.json中的词表。
+    ///
+    /// # 参数
+    ///
+    /// * `vocabulary` - 音素词表。
+    pub fn with_vocabulary(mut self, vocabulary: Vocabulary) -> Self {
+        let mut hasher = Fnv::default();
+        hasher.write(&self.settings.fingerprint.to_le_bytes());
+        for (symbol, id) in vocabulary.symbols() {
+            hasher.write(&(symbol as u32).to_le_bytes());
+            hasher.write(&id.to_le_bytes());
+        }
+        self.settings.fingerprint = hasher.finish();
+        self.settings.vocabulary = Some(Arc::new(vocabulary));
+        self
+    }
+
+    /// 启用严格的音素检查
+    ///
+    /// 默认情况下，词表外的音素会先映射到最接近的受支持音素，无法映射的音素会被丢弃并记录警告，
+    /// 这可能导致合成的语音缺少部分读音。启用后遇到无法映射的音素时合成会返回[`KokoroError::UnknownPhoneme`]。
+    ///
+    /// # 参数
+    ///
+    /// * `strict` - 是否启用严格检查。
+    pub fn with_strict_tokens(mut self, strict: bool) -> Self {
+        self.settings.strict_tokens = strict;
+        self
+    }
+
+    /// 指定英语的发音变体
+    ///
+    /// 默认由发音人决定，参见[`Voice::english_variant`]，例如可以让英式发音人朗读美式拼写的文本。
+    ///
+    /// # 参数
+    ///
+    /// * `variant` - 英语发音变体。
+    pub fn with_english_variant(mut self, variant: EnglishVariant) -> Self {
+        let mut hasher = Fnv::default();
+        hasher.write(&self.settings.fingerprint.to_le_bytes());
+        hasher.write(&[variant as u8]);
+        self.settings.fingerprint = hasher.finish();
+        self.settings.english_variant = Some(variant);
+        self
+    }
+
+    /// 设置cmudict读音选择的随机种子，参见[`G2p::with_cmudict_seed`]
+    ///
+    /// # 参数
+    ///
+    /// * `seed` - 随机种子，传入`None`恢复默认行为。
+    #[cfg(feature = "use-cmudict")]
+    pub fn with_cmudict_seed(mut self, seed: Option<u64>) -> Result<Self, KokoroError> {
+        let mut hasher = Fnv::default();
+        hasher.write(&self.settings.fingerprint.to_le_bytes());
+        if let Some(seed) = seed {
+            hasher.write(&seed.to_le_bytes());
+        }
+        self.settings.fingerprint = hasher.finish();
+        // 合成流仍然持有引擎时，创建一个新的引擎
+        let g2p = match Arc::try_unwrap(self.g2p) {
+            Ok(g2p) => g2p,
+            Err(_) => G2p::new()?,
+        };
+        self.g2p = Arc::new(g2p.with_cmudict_seed(seed));
+        Ok(self)
+    }
+
+    /// 在标点、换行和空行处插入指定时长的停顿
+    ///
+    /// 默认由模型自行决定停顿，换行会被忽略。启用后文本在这些位置被切开分别合成，段之间插入[`Pauses`]中对应的静音，
+    /// 使列表、诗歌和界面提示的节奏可以预测。流式合成时在每次发送的文本内生效。
+    ///
+    /// # 参数
+    ///
+    /// * `pauses` - 各类停顿的时长。
+    pub fn with_pauses(mut self, pauses: Pauses) -> Self {
+        self.pauses = Some(pauses);
+        self
+    }
+
+    /// 启用缓存
+    ///
+    /// 之后合成相同的文本、音色和语速时会直接返回缓存中的音频。缓存的键包含模型、音色文件以及词表等影响合成结果的设置，
+    /// 同一个缓存可以被多个实例共享，各自的条目互不影响。
+    ///
+    /// # 参数
+    ///
+    /// * `cache` - 缓存。
+    pub fn with_cache(mut self, cache: Arc<SynthCache>) -> Self {
+        self.settings.cache = Some(cache);
+        self
+    }
+
+    /// 获取正在使用的缓存
+    pub fn cache(&self) -> Option<&SynthCache> {
+        self.settings.cache.as_deref()
+    }
+
     pub async fn synth<S>(&self, text: S, voice: Voice) -> Result<(Vec<f32>, Duration), KokoroError>
     where
         S: AsRef<str>,
     {
-        let name = voice.get_name();
+        let synthesized = self.synthesize(text, voice).await?;
+        Ok((synthesized.audio, synthesized.took))
+    }
+
+    /// 合成语音并生成对应的字幕
+    ///
+    /// 文本按句子切分后逐句合成，每句对应一条字幕。1.1模型根据其输出的时长、1.0模型根据音量去掉每句首尾的静音。
+    /// 字幕可以使用[`to_srt`]或[`to_vtt`]导出；流式合成时可以使用[`SynthStream::captions`]。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use kokoro_tts::{KokoroTts, Voice, to_srt};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin").await else {
+    ///         return;
+    ///     };
+    ///     let Ok((_audio, captions)) = tts.synth_with_captions("你好。今天天气很好。", Voice::Zf003(1.)).await else {
+    ///         return;
+    ///     };
+    ///     println!("{}", to_srt(&captions));
+    /// }
+    /// ```
+    ///
+    pub async fn synth_with_captions<S>(
+        &self,
+        text: S,
+        voice: Voice,
+    ) -> Result<(Vec<f32>, Vec<Cue>), KokoroError>
+    where
+        S: AsRef<str>,
+    {
+        let mut audio = Vec::new();
+        let mut captions = Vec::new();
+        for sentence in split_sentences(text.as_ref()) {
+            let synthesized = self.synthesize(&sentence, voice).await?;
+            let time = |i: usize| from_samples(audio.len() + i);
+            captions.push(Cue {
+                start: time(synthesized.speech.start),
+                end: time(synthesized.speech.end),
+                text: sentence,
+            });
+            audio.extend(synthesized.audio);
+        }
+        Ok((audio, captions))
+    }
+
+    async fn synthesize<S>(&self, text: S, voice: Voice) -> Result<Synthesized, KokoroError>
+    where
+        S: AsRef<str>,
+    {
+        self.model.version.check(&voice)?;
+        let name = voice.name();
         let pack = self
             .voices
             .get(name)
             .ok_or(KokoroError::VoiceNotFound(name.to_owned()))?;
-        synthesizer::synth(Arc::downgrade(&self.model), text, pack, voice).await
+        let synth = |text| {
+            synthesizer::synth(
+                Arc::downgrade(&self.model),
+                &self.g2p,
+                text,
+                pack,
+                voice,
+                &self.settings,
+            )
+        };
+        match &self.pauses {
+            Some(pauses) => pause::synth_with_pauses(text.as_ref(), pauses, synth).await,
+            None => synth(text.as_ref().to_owned()).await,
+        }
+    }
+
+    /// 合成指定时长的语音，用于配音和字幕对齐
+    ///
+    /// 先按发音人的语速合成并测量时长，超出容差时按比例调整语速重新合成，仍然超出容差时再通过保持音高的时间拉伸对齐到目标时长。
+    /// 语速和拉伸的调整范围都是0.5到2倍，超出范围时无法对齐，[`FittedAudio::fits`]为`false`。
+    ///
+    /// # 参数
+    ///
+    /// * `text` - 要合成的文本。
+    /// * `voice` - 发音人。
+    /// * `target` - 目标时长。
+    pub async fn synth_to_duration<S>(
+        &self,
+        text: S,
+        voice: Voice,
+        target: Duration,
+    ) -> Result<FittedAudio, KokoroError>
+    where
+        S: AsRef<str>,
+    {
+        let text = text.as_ref();
+        let synthesized = self.synth(text, voice).await?;
+        fit_to_length(synthesized, to_samples(target), |speed| {
+            self.synth(text, voice.with_speed(voice.speed() * speed))
+        })
+        .await
+    }
+
+    /// 合成语音并去掉首尾的静音
+    async fn synth_speech(
+        &self,
+        text: &str,
+        voice: Voice,
+    ) -> Result<(Vec<f32>, Duration), KokoroError> {
+        let synthesized = self.synthesize(text, voice).await?;
+        Ok((
+            synthesized.audio[synthesized.speech].to_vec(),
+            synthesized.took,
+        ))
+    }
+
+    /// 根据字幕配音
+    ///
+    /// 逐条合成字幕，去掉首尾的静音后放到时间轴上各自的开始时间处，生成一条连续的音轨，字幕之间为静音。
+    /// 比时间段长的配音会被加快，但速度不超过`max_rate`倍，仍然放不下的字幕会记录在[`Dubbing::overflows`]中。
+    ///
+    /// # 参数
+    ///
+    /// * `cues` - 字幕，可以由[`parse_subtitles`]解析得到。
+    /// * `voice` - 发音人。
+    /// * `max_rate` - 最大的加速倍数，例如1.5。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use kokoro_tts::{KokoroTts, SAMPLE_RATE, Voice, parse_subtitles};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin").await else {
+    ///         return;
+    ///     };
+    ///     let cues = parse_subtitles("1\n00:00:01,000 --> 00:00:02,000\n你好\n").unwrap();
+    ///     let Ok(dubbing) = tts.dub(&cues, Voice::Zf003(1.), 1.5).await else {
+    ///         return;
+    ///     };
+    ///     let _wav = dubbing.to_wav(SAMPLE_RATE);
+    ///     for i in dubbing.overflows {
+    ///         println!("{}: {:?}", i.index, i.overflow);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub async fn dub(
+        &self,
+        cues: &[Cue],
+        voice: Voice,
+        max_rate: f32,
+    ) -> Result<Dubbing, KokoroError> {
+        let mut dubbing = Dubbing::default();
+        for (index, cue) in cues.iter().enumerate() {
+            dubbing.pad_to(cue.end);
+            if cue.text.is_empty() {
+                continue;
+            }
+            // 去掉首尾的静音，语音从字幕的开始时间开始，静音也不占用时间段
+            let (audio, took) = self.synth_speech(&cue.text, voice).await?;
+            let slot = to_samples(cue.duration());
+            let audio = if audio.len() > slot {
+                let target = slot.max((audio.len() as f32 / max_rate.max(1.)).ceil() as usize);
+                fit_to_length((audio, took), target, |speed| {
+                    self.synth_speech(&cue.text, voice.with_speed(voice.speed() * speed))
+                })
+                .await?
+                .audio
+            } else {
+                audio
+            };
+            dubbing.place(index, cue, &audio);
+        }
+        Ok(dubbing)
+    }
+
+    /// 合成多人对话
+    ///
+    /// 按顺序合成每句台词，用各自说话人的发音人和语速，拼接为一条音轨，台词之间停顿[`DialogueOptions::turn_gap`]。
+    ///
+    /// # 参数
+    ///
+    /// * `lines` - 台词，可以由[`parse_script`]解析得到。
+    /// * `cast` - 说话人到发音人的映射，脚本中的每个说话人都必须出现。
+    /// * `options` - 停顿和立体声设置。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use {
+    ///     kokoro_tts::{DialogueOptions, KokoroTts, SAMPLE_RATE, Speaker, Voice, parse_script},
+    ///     std::collections::HashMap,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin").await else {
+    ///         return;
+    ///     };
+    ///     let lines = parse_script("甲：你好。\n乙：你好，好久不见。").unwrap();
+    ///     let cast = HashMap::from([
+    ///         ("甲".to_owned(), Speaker { voice: Voice::Zf003(1.), pan: -0.5 }),
+    ///         ("乙".to_owned(), Speaker { voice: Voice::Zm009(1.2), pan: 0.5 }),
+    ///     ]);
+    ///     let options = DialogueOptions { stereo: true, ..Default::default() };
+    ///     if let Ok(dialogue) = tts.synth_dialogue(&lines, &cast, &options).await {
+    ///         let _wav = dialogue.to_wav(SAMPLE_RATE);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub async fn synth_dialogue(
+        &self,
+        lines: &[Line],
+        cast: &HashMap<String, Speaker>,
+        options: &DialogueOptions,
+    ) -> Result<Dialogue, KokoroError> {
+        // 合成前先检查所有说话人，避免合成到一半才失败
+        let speakers = lines
+            .iter()
+            .map(|i| {
+                cast.get(&i.speaker).ok_or_else(|| {
+                    KokoroError::ScriptInvalid(format!("unknown speaker {}", i.speaker))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut dialogue = Dialogue::new(options.stereo);
+        for (line, speaker) in lines.iter().zip(speakers) {
+            let synthesized = self.synthesize(&line.text, speaker.voice).await?;
+            dialogue.push(line, speaker, &synthesized, options.turn_gap);
+        }
+        Ok(dialogue)
+    }
+
+    /// 合成有声书
+    ///
+    /// 每章输出一个WAV文件和逐句对齐的SRT字幕，并在`dir`中写入`index.json`索引。每完成一章都会更新索引，
+    /// 中断后使用相同的书、发音人和语速再次调用时，已完成的章节会被跳过。
+    ///
+    /// # 参数
+    ///
+    /// * `book` - 书，可以由[`Book::from_file`]读取。
+    /// * `voice` - 发音人。
+    /// * `options` - 停顿等设置。
+    /// * `dir` - 输出目录。
+    /// * `progress` - 每合成一句或跳过一章时调用，用于报告进度。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use kokoro_tts::{AudiobookOptions, Book, KokoroTts, Voice};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin").await else {
+    ///         return;
+    ///     };
+    ///     let book = Book::from_text("第一章\n很久以前，有一座山。\n第二章\n山上有座庙。");
+    ///     let _ = tts
+    ///         .synth_audiobook(&book, Voice::Zf003(1.), &AudiobookOptions::default(), "book", |p| {
+    ///             eprintln!("{}/{}", p.done, p.total);
+    ///         })
+    ///         .await;
+    /// }
+    /// ```
+    ///
+    pub async fn synth_audiobook<P, F>(
+        &self,
+        book: &Book,
+        voice: Voice,
+        options: &AudiobookOptions,
+        dir: P,
+        progress: F,
+    ) -> Result<Vec<ChapterEntry>, KokoroError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Progress),
+    {
+        audiobook::synth_audiobook(self, book, voice, options, dir.as_ref(), progress).await
     }
 
     pub fn stream<S>(&self, voice: Voice) -> (SynthSink<S>, SynthStream)
     where
         S: AsRef<str> + Send + 'static,
     {
+        let g2p = Arc::downgrade(&self.g2p);
         let voices = Arc::downgrade(&self.voices);
         let model = Arc::downgrade(&self.model);
+        let settings = self.settings.clone();
+        let version = self.model.version;
+        let pauses = self.pauses;
 
-        start_synth_session(voice, move |text, voice| {
+        start_synth_session(voice, move |text: S, voice| {
+            let g2p = g2p.clone();
             let voices = voices.clone();
             let model = model.clone();
+            let settings = settings.clone();
             async move {
-                let name = voice.get_name();
+                version.check(&voice)?;
+                let name = voice.name();
+                let g2p = g2p.upgrade().ok_or(KokoroError::ModelReleased)?;
                 let voices = voices.upgrade().ok_or(KokoroError::ModelReleased)?;
                 let pack = voices
                     .get(name)
                     .ok_or(KokoroError::VoiceNotFound(name.to_owned()))?;
-                synthesizer::synth(model, text, pack, voice).await
+                let synth =
+                    |text| synthesizer::synth(model.clone(), &g2p, text, pack, voice, &settings);
+                match &pauses {
+                    Some(pauses) => pause::synth_with_pauses(text.as_ref(), pauses, synth).await,
+                    None => synth(text.as_ref().to_owned()).await,
+                }
             }
         })
     }
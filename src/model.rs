@@ -0,0 +1,146 @@
+use {
+    crate::{KokoroError, Voice},
+    futures::lock::Mutex,
+    ort::{session::Session, tensor::TensorElementType},
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// 已加载的模型及其版本
+pub(super) struct Model {
+    pub(super) session: Mutex<Session>,
+    pub(super) version: ModelVersion,
+}
+
+impl Model {
+    pub(super) fn new(session: Session) -> Result<Self, KokoroError> {
+        Ok(Self {
+            version: ModelVersion::detect(&session)?,
+            session: session.into(),
+        })
+    }
+}
+
+/// 模型版本
+///
+/// 加载模型时根据其输入输出自动识别，决定合成时使用的音素、词表和推理参数。
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ModelVersion {
+    /// 1.0模型，输入为`tokens`、`style`和浮点数`speed`，输出为`audio`
+    V10,
+    /// 1.1模型，输入为`input_ids`、`style`和整数`speed`，输出为`waveform`和`duration`
+    V11,
+}
+
+impl ModelVersion {
+    /// 根据已加载的会话识别模型版本
+    fn detect(session: &Session) -> Result<Self, KokoroError> {
+        let inputs = session
+            .inputs()
+            .iter()
+            .map(|i| (i.name(), i.dtype().tensor_type()))
+            .collect::<Vec<_>>();
+        let outputs = session
+            .outputs()
+            .iter()
+            .map(|o| o.name())
+            .collect::<Vec<_>>();
+        Self::from_signature(&inputs, &outputs)
+    }
+
+    fn from_signature(
+        inputs: &[(&str, Option<TensorElementType>)],
+        outputs: &[&str],
+    ) -> Result<Self, KokoroError> {
+        let has_input = |name| inputs.iter().any(|(n, _)| *n == name);
+        let speed = inputs.iter().find(|(n, _)| *n == "speed").map(|(_, t)| *t);
+        let version = match speed {
+            Some(Some(TensorElementType::Float32))
+                if has_input("tokens") && outputs.contains(&"audio") =>
+            {
+                Some(Self::V10)
+            }
+            Some(Some(TensorElementType::Int32))
+                if has_input("input_ids")
+                    && outputs.contains(&"waveform")
+                    && outputs.contains(&"duration") =>
+            {
+                Some(Self::V11)
+            }
+            _ => None,
+        };
+
+        match version {
+            Some(v) if has_input("style") => Ok(v),
+            _ => Err(KokoroError::ModelUnsupported(format!(
+                "inputs: {:?}, outputs: {:?}",
+                inputs, outputs
+            ))),
+        }
+    }
+
+    /// 该版本的模型是否可以使用指定的发音人
+    pub fn supports(&self, voice: &Voice) -> bool {
+        match self {
+            Self::V10 => voice.is_v10_supported(),
+            Self::V11 => voice.is_v11_supported(),
+        }
+    }
+
+    /// 检查发音人是否可以用于该版本的模型
+    pub(super) fn check(&self, voice: &Voice) -> Result<(), KokoroError> {
+        if self.supports(voice) {
+            Ok(())
+        } else {
+            Err(KokoroError::VoiceModelMismatch(
+                voice.name().to_owned(),
+                *self,
+            ))
+        }
+    }
+}
+
+impl Display for ModelVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::V10 => write!(f, "v1.0"),
+            Self::V11 => write!(f, "v1.1"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_signature() {
+        use TensorElementType::{Float32, Int32, Int64};
+
+        let v10 = [
+            ("tokens", Some(Int64)),
+            ("style", Some(Float32)),
+            ("speed", Some(Float32)),
+        ];
+        let v11 = [
+            ("input_ids", Some(Int64)),
+            ("style", Some(Float32)),
+            ("speed", Some(Int32)),
+        ];
+        assert_eq!(
+            Some(ModelVersion::V10),
+            ModelVersion::from_signature(&v10, &["audio"]).ok()
+        );
+        assert_eq!(
+            Some(ModelVersion::V11),
+            ModelVersion::from_signature(&v11, &["waveform", "duration"]).ok()
+        );
+        assert!(ModelVersion::from_signature(&v10, &["waveform", "duration"]).is_err());
+        assert!(ModelVersion::from_signature(&v11[..2], &["waveform", "duration"]).is_err());
+
+        assert!(ModelVersion::V11.check(&Voice::Zf003(1.)).is_ok());
+        assert!(matches!(
+            ModelVersion::V10.check(&Voice::Zf003(1.)),
+            Err(KokoroError::VoiceModelMismatch(_, ModelVersion::V10))
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use {
+    crate::{KokoroError, audio::to_samples, synthesizer::Synthesized},
+    std::{ops::Range, time::Duration},
+};
+
+/// 标点和换行处的停顿时长，参见[`crate::KokoroTts::with_pauses`]
+///
+/// 启用后文本在这些位置被切开分别合成，每段去掉首尾的静音后再插入指定时长的静音，标点仍然保留在前一段中以保持语调。
+/// 同一处同时有标点和换行时取较长的停顿，时长为零的类别不切分。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pauses {
+    /// 逗号和顿号：`，`、`,`、`、`
+    pub comma: Duration,
+    /// 分号、冒号和破折号：`；`、`;`、`：`、`:`、`——`
+    pub clause: Duration,
+    /// 句末的句号、问号、叹号和省略号
+    pub sentence: Duration,
+    /// 单个换行
+    pub line_break: Duration,
+    /// 空行
+    pub paragraph: Duration,
+}
+
+impl Default for Pauses {
+    fn default() -> Self {
+        Self {
+            comma: Duration::from_millis(200),
+            clause: Duration::from_millis(300),
+            sentence: Duration::from_millis(400),
+            line_break: Duration::from_millis(500),
+            paragraph: Duration::from_millis(900),
+        }
+    }
+}
+
+impl Pauses {
+    /// 标点的停顿，不切分的字符返回`None`
+    fn punctuation(&self, c: char, next: Option<char>) -> Option<Duration> {
+        // 英文标点后面必须是空白，避免切开小数、千分位和缩写
+        let spaced = next.is_none_or(char::is_whitespace);
+        match c {
+            '，' | '、' => Some(self.comma),
+            ',' if spaced => Some(self.comma),
+            '；' | '：' | '—' => Some(self.clause),
+            ';' | ':' if spaced => Some(self.clause),
+            '。' | '！' | '？' | '…' => Some(self.sentence),
+            '.' | '!' | '?' if spaced => Some(self.sentence),
+            _ => None,
+        }
+    }
+
+    /// 把文本切分为若干段，每段之后跟随一个停顿，最后一段之后的停顿为零
+    fn split(&self, text: &str) -> Vec<(String, Duration)> {
+        let mut ret = Vec::new();
+        let mut current = String::new();
+        let mut pause = Duration::ZERO;
+        let mut newlines = 0;
+        let mut chars = text.trim().chars().peekable();
+        while let Some(c) = chars.next() {
+            if let Some(p) = self.punctuation(c, chars.peek().copied()) {
+                current.push(c);
+                pause = pause.max(p);
+                continue;
+            }
+            if c == '\n' {
+                newlines += 1;
+                continue;
+            }
+            // 引号、括号等紧跟在标点后面的字符属于前一段
+            if c.is_whitespace() || (pause > Duration::ZERO && is_closing(c)) {
+                current.push(c);
+                continue;
+            }
+
+            pause = pause.max(match newlines {
+                0 => Duration::ZERO,
+                1 => self.line_break,
+                _ => self.paragraph,
+            });
+            if pause > Duration::ZERO {
+                ret.push((current.trim().to_owned(), pause));
+                current.clear();
+            } else if newlines > 0 {
+                current.push(' ');
+            }
+            pause = Duration::ZERO;
+            newlines = 0;
+            current.push(c);
+        }
+        ret.push((current.trim().to_owned(), Duration::ZERO));
+        ret
+    }
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(
+        c,
+        '"' | '\'' | ')' | ']' | '”' | '’' | '」' | '』' | '）' | '》' | '】'
+    )
+}
+
+/// 分段合成并在段之间插入停顿
+///
+/// 只有一段时直接合成，保持与不使用停顿时相同的结果。
+pub(super) async fn synth_with_pauses<F, R>(
+    text: &str,
+    pauses: &Pauses,
+    mut synth: F,
+) -> Result<Synthesized, KokoroError>
+where
+    F: FnMut(String) -> R,
+    R: Future<Output = Result<Synthesized, KokoroError>>,
+{
+    let segments = pauses.split(text);
+    if segments.len() < 2 {
+        return synth(text.to_owned()).await;
+    }
+
+    let mut audio = Vec::<f32>::new();
+    let mut took = Duration::ZERO;
+    let mut speech = None::<Range<usize>>;
+    let last = segments.len() - 1;
+    for (index, (segment, pause)) in segments.into_iter().enumerate() {
+        // 只有标点的段只产生停顿
+        if segment.chars().any(char::is_alphanumeric) {
+            let synthesized = synth(segment).await?;
+            took += synthesized.took;
+            let range = synthesized.speech;
+            // 保留整句开头和结尾的静音，段之间的静音由停顿代替
+            let from = if speech.is_none() { 0 } else { range.start };
+            let to = if index == last {
+                synthesized.audio.len()
+            } else {
+                range.end
+            };
+            let base = audio.len();
+            audio.extend_from_slice(&synthesized.audio[from..to]);
+            let start = speech.map_or(base + range.start, |i| i.start);
+            speech = Some(start..base + range.end - from);
+        }
+        if speech.is_some() {
+            audio.resize(audio.len() + to_samples(pause), 0.);
+        }
+    }
+
+    match speech {
+        Some(speech) => Ok(Synthesized {
+            audio,
+            took,
+            speech,
+        }),
+        None => synth(text.to_owned()).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let pauses = Pauses::default();
+        let ms = Duration::from_millis;
+        assert_eq!(
+            vec![
+                ("你轻轻地走过那".to_owned(), ms(500)),
+                ("在风雨花丛中，".to_owned(), ms(500)),
+                ("“是梦吗？”".to_owned(), ms(900)),
+                ("Pi is 3.14,".to_owned(), ms(500)),
+                ("right?".to_owned(), ms(0)),
+            ],
+            pauses.split("你轻轻地走过那\n在风雨花丛中，\n“是梦吗？”\n\nPi is 3.14,\nright?")
+        );
+
+        let pauses = Pauses {
+            comma: Duration::ZERO,
+            ..pauses
+        };
+        assert_eq!(
+            vec![
+                ("你好，世界。".to_owned(), ms(400)),
+                ("再见".to_owned(), ms(0))
+            ],
+            pauses.split("你好，世界。再见")
+        );
+    }
+
+    #[test]
+    fn test_synth_with_pauses() -> Result<(), KokoroError> {
+        let pauses = Pauses {
+            line_break: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let synthesized =
+            futures::executor::block_on(synth_with_pauses("一\n二", &pauses, |_| async {
+                Ok(Synthesized {
+                    audio: vec![1.; 100],
+                    took: Duration::from_millis(1),
+                    speech: 10..90,
+                })
+            }))?;
+        // 第一段保留开头的静音，第二段保留结尾的静音，中间为240个采样的停顿
+        assert_eq!(90 + 240 + 90, synthesized.audio.len());
+        assert_eq!(10..90 + 240 + 80, synthesized.speech);
+        assert_eq!(0., synthesized.audio[90]);
+        assert_eq!(Duration::from_millis(2), synthesized.took);
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use {
+    crate::{
+        Cue, EnglishVariant, G2p, KokoroError, SAMPLE_RATE, Voice,
+        blocking::{KokoroTts, SynthSink, SynthStream},
+        get_token_ids, split_sentences,
+    },
+    numpy::PyArray1,
+    pyo3::{
+        exceptions::{PyRuntimeError, PyValueError},
+        prelude::*,
+    },
+    std::time::Duration,
+};
+
+impl From<KokoroError> for PyErr {
+    fn from(value: KokoroError) -> Self {
+        PyRuntimeError::new_err(value.to_string())
+    }
+}
+
+fn to_variant(variant: &str) -> PyResult<EnglishVariant> {
+    match variant {
+        "american" | "en-us" => Ok(EnglishVariant::American),
+        "british" | "en-gb" => Ok(EnglishVariant::British),
+        v => Err(PyValueError::new_err(format!(
+            "Unknown English variant: {}",
+            v
+        ))),
+    }
+}
+
+/// `(开始秒数, 结束秒数, 文本)`形式的字幕
+type Captions = Vec<(f64, f64, String)>;
+
+fn to_tuples(cues: &[Cue]) -> Captions {
+    cues.iter()
+        .map(|i| (i.start.as_secs_f64(), i.end.as_secs_f64(), i.text.clone()))
+        .collect()
+}
+
+/// 语音合成器
+#[pyclass(name = "KokoroTts", module = "kokoro_tts", frozen)]
+struct PyKokoroTts {
+    tts: KokoroTts,
+}
+
+#[pymethods]
+impl PyKokoroTts {
+    /// 从模型文件和发音人文件创建合成器
+    #[new]
+    fn new(py: Python<'_>, model_path: &str, voices_path: &str) -> PyResult<Self> {
+        let tts = py.detach(|| KokoroTts::new(model_path, voices_path))?;
+        Ok(Self { tts })
+    }
+
+    /// 从内存中的模型和发音人数据创建合成器
+    #[staticmethod]
+    fn from_bytes(py: Python<'_>, model: &[u8], voices: &[u8]) -> PyResult<Self> {
+        let tts = py.detach(|| KokoroTts::new_from_bytes(model, voices))?;
+        Ok(Self { tts })
+    }
+
+    /// 模型版本，"v1.0"或"v1.1"
+    #[getter]
+    fn model_version(&self) -> String {
+        self.tts.model_version().to_string()
+    }
+
+    /// 所有发音人的名称
+    #[getter]
+    fn voices(&self) -> Vec<String> {
+        let mut names = self
+            .tts
+            .voice_names()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// 合成语音，返回float32的numpy数组
+    #[pyo3(signature = (text, voice, speed = 1.))]
+    fn synth<'py>(
+        &self,
+        py: Python<'py>,
+        text: &str,
+        voice: &str,
+        speed: f32,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        let voice = Voice::from_name(voice, speed)?;
+        let (audio, _) = py.detach(|| self.tts.synth(text, voice))?;
+        Ok(PyArray1::from_vec(py, audio))
+    }
+
+    /// 合成指定时长（秒）的语音，返回音频、总的速度变化倍数和时长是否在容差以内
+    #[pyo3(signature = (text, voice, duration, speed = 1.))]
+    fn synth_to_duration<'py>(
+        &self,
+        py: Python<'py>,
+        text: &str,
+        voice: &str,
+        duration: f64,
+        speed: f32,
+    ) -> PyResult<(Bound<'py, PyArray1<f32>>, f32, bool)> {
+        let voice = Voice::from_name(voice, speed)?;
+        let target = Duration::try_from_secs_f64(duration)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let fitted = py.detach(|| self.tts.synth_to_duration(text, voice, target))?;
+        let rate = fitted.rate();
+        Ok((PyArray1::from_vec(py, fitted.audio), rate, fitted.fits))
+    }
+
+    /// 合成语音并生成按句子对齐的字幕，字幕为`(开始秒数, 结束秒数, 文本)`的列表
+    #[pyo3(signature = (text, voice, speed = 1.))]
+    fn synth_with_captions<'py>(
+        &self,
+        py: Python<'py>,
+        text: &str,
+        voice: &str,
+        speed: f32,
+    ) -> PyResult<(Bound<'py, PyArray1<f32>>, Captions)> {
+        let voice = Voice::from_name(voice, speed)?;
+        let (audio, captions) = py.detach(|| self.tts.synth_with_captions(text, voice))?;
+        Ok((PyArray1::from_vec(py, audio), to_tuples(&captions)))
+    }
+
+    /// 开始流式合成，`text`不为空时按句子发送后立即结束输入
+    #[pyo3(signature = (voice, speed = 1., text = None))]
+    fn stream(&self, voice: &str, speed: f32, text: Option<&str>) -> PyResult<PySynthStream> {
+        let voice = Voice::from_name(voice, speed)?;
+        let (sink, stream) = self.tts.stream(voice);
+        let mut stream = PySynthStream {
+            sink: Some(sink),
+            stream,
+        };
+        if let Some(text) = text {
+            stream.synth(text)?;
+            stream.close();
+        }
+        Ok(stream)
+    }
+}
+
+/// 流式合成，可以作为迭代器逐句获取音频
+#[pyclass(name = "SynthStream", module = "kokoro_tts")]
+struct PySynthStream {
+    sink: Option<SynthSink<String>>,
+    stream: SynthStream,
+}
+
+impl PySynthStream {
+    fn sink(&mut self) -> PyResult<&mut SynthSink<String>> {
+        self.sink
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("SynthStream is closed"))
+    }
+}
+
+#[pymethods]
+impl PySynthStream {
+    /// 按句子发送要合成的文本
+    fn synth(&mut self, text: &str) -> PyResult<()> {
+        let sink = self.sink()?;
+        for sentence in split_sentences(text) {
+            sink.synth(sentence)?;
+        }
+        Ok(())
+    }
+
+    /// 切换之后发送的文本使用的发音人
+    #[pyo3(signature = (voice, speed = 1.))]
+    fn set_voice(&mut self, voice: &str, speed: f32) -> PyResult<()> {
+        let voice = Voice::from_name(voice, speed)?;
+        self.sink()?.set_voice(voice);
+        Ok(())
+    }
+
+    /// 打断正在进行的合成，丢弃尚未读取的音频
+    fn cancel(&mut self) -> PyResult<()> {
+        self.sink()?.cancel();
+        Ok(())
+    }
+
+    /// 已读取的音频对应的字幕，为`(开始秒数, 结束秒数, 文本)`的列表
+    #[getter]
+    fn captions(&self) -> Captions {
+        to_tuples(self.stream.captions())
+    }
+
+    /// 取出上次取出之后新增的字幕，长时间运行的会话应使用该方法
+    fn take_captions(&mut self) -> Captions {
+        to_tuples(&self.stream.take_captions())
+    }
+
+    /// 结束输入，已发送的文本合成完后迭代结束
+    fn close(&mut self) {
+        self.sink = None;
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyArray1<f32>>>> {
+        let stream = &mut self.stream;
+        match py.detach(|| stream.next()) {
+            Some((audio, _)) => Ok(Some(PyArray1::from_vec(py, audio))),
+            // 合成失败时迭代结束并抛出异常
+            None => match stream.take_error() {
+                Some(e) => Err(e.into()),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+/// 文本转音素
+#[pyclass(name = "G2p", module = "kokoro_tts", frozen)]
+struct PyG2p {
+    g2p: G2p,
+}
+
+#[pymethods]
+impl PyG2p {
+    #[new]
+    fn new() -> PyResult<Self> {
+        Ok(Self {
+            g2p: G2p::new().map_err(KokoroError::from)?,
+        })
+    }
+
+    /// 把文本转换为音素，`v11`为真时使用1.1模型的中文音素（包含连读变调）
+    #[pyo3(signature = (text, v11 = true, variant = "american"))]
+    fn g2p(&self, py: Python<'_>, text: &str, v11: bool, variant: &str) -> PyResult<String> {
+        let variant = to_variant(variant)?;
+        py.detach(|| self.g2p.g2p(text, v11, variant))
+            .map_err(|e| KokoroError::from(e).into())
+    }
+}
+
+/// 使用全局共享的转换器把文本转换为音素
+#[pyfunction(name = "g2p")]
+#[pyo3(signature = (text, v11 = true, variant = "american"))]
+fn py_g2p(py: Python<'_>, text: &str, v11: bool, variant: &str) -> PyResult<String> {
+    let variant = to_variant(variant)?;
+    py.detach(|| crate::g2p_with_variant(text, v11, variant))
+        .map_err(|e| KokoroError::from(e).into())
+}
+
+/// 把音素转换为模型输入的token id
+#[pyfunction(name = "get_token_ids")]
+#[pyo3(signature = (phonemes, v11 = true))]
+fn py_get_token_ids(phonemes: &str, v11: bool) -> Vec<i64> {
+    get_token_ids(phonemes, v11)
+}
+
+#[pymodule]
+#[pyo3(name = "kokoro_tts")]
+fn init_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("SAMPLE_RATE", SAMPLE_RATE)?;
+    m.add_class::<PyKokoroTts>()?;
+    m.add_class::<PySynthStream>()?;
+    m.add_class::<PyG2p>()?;
+    m.add_function(wrap_pyfunction!(py_g2p, m)?)?;
+    m.add_function(wrap_pyfunction!(py_get_token_ids, m)?)?;
+    Ok(())
+}
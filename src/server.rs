@@ -0,0 +1,303 @@
+use {
+    crate::{
+        FlacEncoder, KokoroTts, SAMPLE_RATE, SynthSink, SynthStream, Voice, split_sentences,
+        to_pcm_s16le, wav_header,
+    },
+    axum::{
+        Json, Router,
+        body::{Body, Bytes},
+        extract::State,
+        http::{StatusCode, header},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+    },
+    futures::{StreamExt, stream},
+    log::error,
+    serde::Deserialize,
+    serde_json::json,
+    std::{ops::RangeInclusive, sync::Arc},
+};
+
+type StreamFactory = dyn Fn(Voice) -> (SynthSink<String>, SynthStream) + Send + Sync;
+
+/// 路由共享的状态：排好序的发音人名称和开始流式合成的方法
+struct Service {
+    voices: Vec<String>,
+    stream: Box<StreamFactory>,
+}
+
+#[derive(Deserialize)]
+struct SpeechRequest {
+    input: String,
+    voice: String,
+    response_format: Option<String>,
+    #[serde(default = "default_speed")]
+    speed: f32,
+}
+
+fn default_speed() -> f32 {
+    1.
+}
+
+/// OpenAI接口允许的语速范围
+const SPEED_RANGE: RangeInclusive<f32> = 0.25..=4.;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Wav,
+    Pcm,
+    Flac,
+}
+
+impl Format {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Wav => "audio/wav",
+            Self::Pcm => "audio/pcm",
+            Self::Flac => "audio/flac",
+        }
+    }
+}
+
+fn error<S: Into<String>>(status: StatusCode, message: S) -> Response {
+    let body = json!({
+        "error": {
+            "message": message.into(),
+            "type": "invalid_request_error",
+        }
+    });
+    (status, Json(body)).into_response()
+}
+
+/// 查找发音人，OpenAI的发音人名称（例如alloy）会匹配到同名的发音人（例如af_alloy）
+fn resolve_voice<'a>(voices: &'a [String], name: &str) -> Option<&'a str> {
+    let suffix = format!("_{}", name);
+    voices
+        .iter()
+        .find(|i| *i == name)
+        .or_else(|| voices.iter().find(|i| i.ends_with(&suffix)))
+        .map(String::as_str)
+}
+
+async fn speech(State(service): State<Arc<Service>>, Json(req): Json<SpeechRequest>) -> Response {
+    let format = match req.response_format.as_deref().unwrap_or("wav") {
+        "wav" => Format::Wav,
+        "pcm" => Format::Pcm,
+        "flac" => Format::Flac,
+        f => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported response_format: {}", f),
+            );
+        }
+    };
+    // NaN不在范围内，同样会被拒绝
+    if !SPEED_RANGE.contains(&req.speed) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Speed must be between {} and {}: {}",
+                SPEED_RANGE.start(),
+                SPEED_RANGE.end(),
+                req.speed
+            ),
+        );
+    }
+    let sentences = split_sentences(&req.input);
+    if sentences.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "Input is empty");
+    }
+    let Some(name) = resolve_voice(&service.voices, &req.voice) else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!("Voice not found: {}", req.voice),
+        );
+    };
+    let voice = match Voice::from_name(name, req.speed) {
+        Ok(v) => v,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+
+    let (mut sink, audio) = (service.stream)(voice);
+    for sentence in sentences {
+        if let Err(e) = sink.synth(sentence).await {
+            return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        }
+    }
+    // 关闭发送端，所有句子合成完后音频流随之结束
+    drop(sink);
+
+    let flac = matches!(format, Format::Flac).then(|| FlacEncoder::new(SAMPLE_RATE));
+    let header = match (&format, &flac) {
+        (Format::Wav, _) => wav_header(SAMPLE_RATE, u32::MAX),
+        (_, Some(encoder)) => encoder.header(0),
+        _ => Vec::new(),
+    };
+    let chunks = stream::unfold((audio, flac), |(mut audio, mut flac)| async move {
+        match audio.next().await {
+            Some((data, _)) => {
+                let data = match flac.as_mut() {
+                    Some(encoder) => encoder.encode(&data),
+                    None => to_pcm_s16le(&data),
+                };
+                Some((Ok(data), (audio, flac)))
+            }
+            None => match audio.take_error() {
+                // 响应头已经发出，只能中止分块传输，让客户端知道音频不完整
+                Some(e) => {
+                    error!("Synthesis failed: {}", e);
+                    Some((Err(e), (audio, None)))
+                }
+                None => flac
+                    .take()
+                    .map(|mut encoder| (Ok(encoder.finish()), (audio, None))),
+            },
+        }
+    });
+    let body = stream::once(async move { Ok(header) })
+        .chain(chunks)
+        .map(|i| i.map(Bytes::from));
+
+    (
+        [(header::CONTENT_TYPE, format.content_type())],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+async fn voices(State(service): State<Arc<Service>>) -> Json<serde_json::Value> {
+    Json(json!({ "voices": service.voices }))
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+fn router<F>(names: Vec<String>, stream: F) -> Router
+where
+    F: Fn(Voice) -> (SynthSink<String>, SynthStream) + Send + Sync + 'static,
+{
+    let service = Service {
+        voices: names,
+        stream: Box::new(stream),
+    };
+    Router::new()
+        .route("/v1/audio/speech", post(speech))
+        .route("/v1/voices", get(voices))
+        .route("/health", get(health))
+        .with_state(Arc::new(service))
+}
+
+/// 创建兼容OpenAI语音合成接口的HTTP路由
+///
+/// 包括`POST /v1/audio/speech`、`GET /v1/voices`和`GET /health`，音频按句子合成并以分块传输的方式返回，
+/// 合成中途失败时分块传输会被中止。
+///
+/// # 参数
+///
+/// * `tts` - 语音合成器。
+pub fn openai_router(tts: Arc<KokoroTts>) -> Router {
+    let mut names = tts.voice_names().map(ToOwned::to_owned).collect::<Vec<_>>();
+    names.sort_unstable();
+    router(names, move |voice| tts.stream(voice))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{KokoroError, start_synth_session, synthesizer::Synthesized},
+        axum::{body::to_bytes, http::Request},
+        serde_json::Value,
+        std::time::Duration,
+        tower::ServiceExt,
+    };
+
+    fn app() -> Router {
+        let names = vec!["af_alloy".to_owned(), "zf_xiaoxiao".to_owned()];
+        router(names, |voice| {
+            // 每句话合成出100个采样的静音，包含“坏”字的句子合成失败
+            start_synth_session(voice, |text: String, _| async move {
+                match text.contains('坏') {
+                    true => Err(KokoroError::VoiceNotFound(text)),
+                    false => Ok(Synthesized::new(vec![0.; 100], Duration::ZERO)),
+                }
+            })
+        })
+    }
+
+    async fn get(uri: &str) -> (StatusCode, Value) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn speech(body: Value) -> Response {
+        let request = Request::post("/v1/audio/speech")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app().oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        assert_eq!(
+            (StatusCode::OK, json!({"status": "ok"})),
+            get("/health").await
+        );
+        assert_eq!(
+            (
+                StatusCode::OK,
+                json!({"voices": ["af_alloy", "zf_xiaoxiao"]})
+            ),
+            get("/v1/voices").await
+        );
+
+        let response = speech(json!({"input": "你好。世界。", "voice": "alloy"})).await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("audio/wav", response.headers()[header::CONTENT_TYPE]);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(44 + 2 * 100 * 2, body.len());
+        assert_eq!(b"RIFF", &body[..4]);
+
+        let response =
+            speech(json!({"input": "你好。", "voice": "zf_xiaoxiao", "response_format": "flac"}))
+                .await;
+        assert_eq!("audio/flac", response.headers()[header::CONTENT_TYPE]);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(b"fLaC", &body[..4]);
+
+        for (request, message) in [
+            (
+                json!({"input": "你好。", "voice": "alloy", "response_format": "mp3"}),
+                "Unsupported response_format: mp3",
+            ),
+            (json!({"input": "", "voice": "alloy"}), "Input is empty"),
+            (
+                json!({"input": "你好。", "voice": "alloy", "speed": 5}),
+                "Speed must be between 0.25 and 4: 5",
+            ),
+            (
+                json!({"input": "你好。", "voice": "nova"}),
+                "Voice not found: nova",
+            ),
+        ] {
+            let response = speech(request).await;
+            assert_eq!(StatusCode::BAD_REQUEST, response.status());
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body = serde_json::from_slice::<Value>(&body).unwrap();
+            assert_eq!(message, body["error"]["message"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_chunk() {
+        // 第一句已经发出，第二句失败时响应体以错误结束而不是正常结束
+        let response = speech(json!({"input": "你好。坏了。再见。", "voice": "alloy"})).await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(to_bytes(response.into_body(), usize::MAX).await.is_err());
+    }
+}
@@ -1,44 +1,140 @@
 use {
-    crate::{KokoroError, Voice},
-    futures::{Sink, SinkExt, Stream},
+    crate::{Cue, KokoroError, Voice, audio::from_samples, synthesizer::Synthesized},
+    futures::{
+        Sink, SinkExt, Stream, StreamExt,
+        channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded},
+        future::BoxFuture,
+        task::AtomicWaker,
+    },
     pin_project::pin_project,
     std::{
+        mem::take,
         pin::Pin,
+        sync::{
+            Arc, Mutex, PoisonError,
+            atomic::{AtomicU64, Ordering},
+        },
         task::{Context, Poll},
         time::Duration,
     },
-    tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
 };
 
-struct Request<S> {
-    voice: Voice,
-    text: S,
+type SynthFuture = BoxFuture<'static, Result<Synthesized, KokoroError>>;
+
+struct Request {
+    synth: SynthFuture,
+    text: String,
+    generation: u64,
 }
 
-struct Response {
-    data: Vec<f32>,
-    took: Duration,
+/// 发送端和合成流共享的取消状态
+#[derive(Default)]
+struct Cancellation {
+    generation: AtomicU64,
+    waker: AtomicWaker,
+}
+
+impl Cancellation {
+    fn current(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
 }
 
 /// 语音合成流
 ///
 /// 该结构体用于通过流式合成来处理更长的文本。它实现了`Stream` trait，可以用于异步迭代合成后的音频数据。
+/// 合成在轮询该流时进行，不依赖特定的异步运行时。
 #[pin_project]
 pub struct SynthStream {
     #[pin]
-    rx: UnboundedReceiver<Response>,
+    rx: UnboundedReceiver<Request>,
+    // 只通过`get_mut`访问，用于让合成流可以在线程间共享
+    current: Mutex<Option<Request>>,
+    cancellation: Arc<Cancellation>,
+    // 已返回的音频的总采样数
+    offset: usize,
+    captions: Vec<Cue>,
+    // 合成失败后流结束，保存失败的原因
+    finished: bool,
+    error: Option<KokoroError>,
+}
+
+impl SynthStream {
+    /// 获取已返回的音频对应的字幕
+    ///
+    /// 每个合成请求对应一条字幕，时间从该流返回的第一段音频开始计算，多个请求的音频首尾相接。
+    /// 字幕会去掉首尾的静音，1.0模型不输出时长，静音根据音量估计。可以使用[`crate::to_srt`]或[`crate::to_vtt`]导出。
+    /// 字幕会一直保留，长时间运行的流应使用[`Self::take_captions`]。
+    pub fn captions(&self) -> &[Cue] {
+        &self.captions
+    }
+
+    /// 取出上次取出之后新增的字幕，时间仍从该流返回的第一段音频开始计算
+    pub fn take_captions(&mut self) -> Vec<Cue> {
+        take(&mut self.captions)
+    }
+
+    /// 取出合成失败的原因
+    ///
+    /// 某个请求合成失败时流随之结束，之后的请求不再处理，与发送端被释放时的正常结束可以通过该方法区分。
+    pub fn take_error(&mut self) -> Option<KokoroError> {
+        self.error.take()
+    }
 }
 
 impl Stream for SynthStream {
     type Item = (Vec<f32>, Duration);
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.project().rx)
-            .poll_recv(cx)
-            .map(|i| match i {
-                None => None,
-                Some(Response { data, took }) => Some((data, took)),
-            })
+        let this = self.project();
+        let mut rx = this.rx;
+        let current = this
+            .current
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if *this.finished {
+            return Poll::Ready(None);
+        }
+        this.cancellation.waker.register(cx.waker());
+        loop {
+            // 丢弃取消之前发送的请求，正在进行的合成也随之中止
+            let generation = this.cancellation.current();
+            if let Some(req) = current.as_mut() {
+                if req.generation != generation {
+                    *current = None;
+                    continue;
+                }
+                let result = match req.synth.as_mut().poll(cx) {
+                    Poll::Ready(result) => result,
+                    Poll::Pending => return Poll::Pending,
+                };
+                let text = take(&mut req.text);
+                *current = None;
+                match result {
+                    Ok(s) => {
+                        let time = |i: usize| from_samples(*this.offset + i);
+                        this.captions.push(Cue {
+                            start: time(s.speech.start),
+                            end: time(s.speech.end),
+                            text,
+                        });
+                        *this.offset += s.audio.len();
+                        return Poll::Ready(Some((s.audio, s.took)));
+                    }
+                    Err(e) => {
+                        *this.finished = true;
+                        *this.error = Some(e);
+                        return Poll::Ready(None);
+                    }
+                }
+            }
+            match rx.poll_next_unpin(cx) {
+                Poll::Ready(Some(req)) if req.generation == generation => *current = Some(req),
+                Poll::Ready(Some(_)) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
     }
 }
 
@@ -47,8 +143,10 @@ impl Stream for SynthStream {
 /// 该结构体用于发送语音合成请求。它实现了`Sink` trait，可以用于异步发送合成请求。
 #[pin_project]
 pub struct SynthSink<S> {
-    tx: UnboundedSender<Request<S>>,
+    tx: UnboundedSender<Request>,
     voice: Voice,
+    synth: Box<dyn Fn(S, Voice) -> SynthFuture + Send + Sync>,
+    cancellation: Arc<Cancellation>,
 }
 
 impl<S> SynthSink<S> {
@@ -103,17 +201,47 @@ impl<S> SynthSink<S> {
     ///     let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin").await else {
     ///         return;
     ///     };
-    ///     let (mut sink, _) =tts.stream(Voice::Zf003(2));
+    ///     let (mut sink, _) =tts.stream(Voice::Zf003(2.));
     ///     let _ = sink.synth("hello world.").await;
     /// }
     /// ```
     ///
-    pub async fn synth(&mut self, text: S) -> Result<(), KokoroError> {
+    pub async fn synth(&mut self, text: S) -> Result<(), KokoroError>
+    where
+        S: AsRef<str>,
+    {
         self.send((self.voice, text)).await
     }
+
+    /// 取消合成
+    ///
+    /// 该方法用于打断正在进行的合成。已发送但尚未合成的请求会被丢弃，正在合成的请求会被中止，
+    /// [`SynthStream`]中尚未读取的音频也不会再返回；之后发送的请求不受影响。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use kokoro_tts::{KokoroTts, Voice};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let Ok(tts) = KokoroTts::new("../kokoro-v1.1-zh.onnx", "../voices-v1.1-zh.bin").await else {
+    ///         return;
+    ///     };
+    ///     let (mut sink, _) = tts.stream(Voice::Zf003(2.));
+    ///     let _ = sink.synth("这是一段很长的文本。").await;
+    ///     sink.cancel();
+    ///     let _ = sink.synth("hello world.").await;
+    /// }
+    /// ```
+    ///
+    pub fn cancel(&self) {
+        self.cancellation.generation.fetch_add(1, Ordering::AcqRel);
+        self.cancellation.waker.wake();
+    }
 }
 
-impl<S> Sink<(Voice, S)> for SynthSink<S> {
+impl<S: AsRef<str>> Sink<(Voice, S)> for SynthSink<S> {
     type Error = KokoroError;
 
     fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -121,8 +249,13 @@ impl<S> Sink<(Voice, S)> for SynthSink<S> {
     }
 
     fn start_send(self: Pin<&mut Self>, (voice, text): (Voice, S)) -> Result<(), Self::Error> {
+        let req = Request {
+            text: text.as_ref().to_owned(),
+            synth: (self.synth)(text, voice),
+            generation: self.cancellation.current(),
+        };
         self.tx
-            .send(Request { voice, text })
+            .unbounded_send(req)
             .map_err(|e| KokoroError::Send(e.to_string()))
     }
 
@@ -135,26 +268,158 @@ impl<S> Sink<(Voice, S)> for SynthSink<S> {
     }
 }
 
-pub(super) fn start_synth_session<'a, F, R, S>(
+/// 按句子切分文本
+///
+/// 将较长的文本按句末标点切分后逐句发送给[`SynthSink`]，可以更早地得到第一段音频。
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let end = match c {
+            '。' | '！' | '？' | '；' | '\n' => true,
+            // 避免切开小数和缩写
+            '.' | '!' | '?' | ';' => chars.peek().is_none_or(|n| n.is_whitespace()),
+            _ => false,
+        };
+        if end {
+            if !current.trim().is_empty() {
+                ret.push(current.trim().to_owned());
+            }
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        ret.push(current.trim().to_owned());
+    }
+    ret
+}
+
+pub(super) fn start_synth_session<F, R, S>(
     voice: Voice,
     synth_request_callback: F,
 ) -> (SynthSink<S>, SynthStream)
 where
-    F: Fn(S, Voice) -> R + Send + 'static,
-    R: Future<Output = Result<(Vec<f32>, Duration), KokoroError>> + Send,
+    F: Fn(S, Voice) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Synthesized, KokoroError>> + Send + 'static,
     S: AsRef<str> + Send + 'static,
 {
-    let (tx, mut rx) = unbounded_channel::<Request<S>>();
-    let (tx2, rx2) = unbounded_channel();
-    tokio::spawn(async move {
-        while let Some(req) = rx.recv().await {
-            let (data, took) = synth_request_callback(req.text, req.voice).await?;
-            tx2.send(Response { data, took })
-                .map_err(|e| KokoroError::Send(e.to_string()))?;
-        }
+    let (tx, rx) = unbounded();
+    let cancellation = Arc::new(Cancellation::default());
+    let synth = move |text, voice| Box::pin(synth_request_callback(text, voice)) as SynthFuture;
 
-        Ok::<_, KokoroError>(())
-    });
+    (
+        SynthSink {
+            tx,
+            voice,
+            synth: Box::new(synth),
+            cancellation: cancellation.clone(),
+        },
+        SynthStream {
+            rx,
+            current: Mutex::new(None),
+            cancellation,
+            offset: 0,
+            captions: Vec::new(),
+            finished: false,
+            error: None,
+        },
+    )
+}
 
-    (SynthSink { tx, voice }, SynthStream { rx: rx2 })
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        tokio::time::{sleep, timeout},
+    };
+
+    #[tokio::test]
+    async fn test_cancel() -> Result<(), KokoroError> {
+        let (mut sink, mut stream) = start_synth_session(Voice::Zf001(1.), |text: &str, _| {
+            let len = text.len();
+            async move {
+                sleep(Duration::from_millis(100)).await;
+                Ok(Synthesized::new(vec![0.; len], Duration::ZERO))
+            }
+        });
+        let consumer = tokio::spawn(async move {
+            let mut lengths = Vec::new();
+            while let Some((audio, _)) = stream.next().await {
+                lengths.push(audio.len());
+            }
+            lengths
+        });
+        sink.synth("a").await?;
+        sink.synth("bb").await?;
+        sleep(Duration::from_millis(50)).await;
+        sink.cancel();
+        sink.synth("ccc").await?;
+        drop(sink);
+
+        let lengths = timeout(Duration::from_millis(200), consumer)
+            .await
+            .expect("Cancelled request was not interrupted")
+            .expect("Consumer panicked");
+        assert_eq!(vec![3], lengths);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_error() -> Result<(), KokoroError> {
+        let (mut sink, mut stream) = start_synth_session(Voice::Zf001(1.), |text: &str, _| {
+            let result = match text {
+                "bad" => Err(KokoroError::VoiceNotFound(text.to_owned())),
+                _ => Ok(Synthesized::new(vec![0.; text.len()], Duration::ZERO)),
+            };
+            async move { result }
+        });
+        sink.synth("a").await?;
+        sink.synth("bad").await?;
+        sink.synth("ccc").await?;
+
+        // 合成失败后流结束，之后的请求不再处理
+        assert_eq!(Some(1), stream.next().await.map(|(a, _)| a.len()));
+        assert!(stream.next().await.is_none());
+        assert!(matches!(
+            stream.take_error(),
+            Some(KokoroError::VoiceNotFound(_))
+        ));
+        assert!(stream.next().await.is_none());
+        assert!(stream.take_error().is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_captions() -> Result<(), KokoroError> {
+        // 每个字符合成1秒的音频，首尾各有0.5秒静音
+        let (mut sink, mut stream) = start_synth_session(Voice::Zf001(1.), |text: &str, _| {
+            let len = text.chars().count() * 24000 + 24000;
+            let audio = Synthesized {
+                audio: vec![0.; len],
+                took: Duration::ZERO,
+                speech: 12000..len - 12000,
+            };
+            async move { Ok(audio) }
+        });
+        sink.synth("你好。").await?;
+        sink.synth("再见").await?;
+        drop(sink);
+        while stream.next().await.is_some() {}
+
+        let secs = Duration::from_secs_f32;
+        let cue = |start, end, text: &str| Cue {
+            start: secs(start),
+            end: secs(end),
+            text: text.to_owned(),
+        };
+        assert_eq!(
+            vec![cue(0.5, 3.5, "你好。"), cue(4.5, 6.5, "再见")],
+            stream.captions()
+        );
+        assert_eq!(2, stream.take_captions().len());
+        assert!(stream.captions().is_empty());
+        Ok(())
+    }
 }
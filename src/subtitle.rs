@@ -0,0 +1,271 @@
+use {
+    crate::{
+        KokoroError, SAMPLE_RATE,
+        audio::{from_samples, to_samples},
+        resample, to_wav,
+    },
+    std::fmt::Write,
+    std::time::Duration,
+};
+
+/// 字幕中的一条
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cue {
+    /// 开始时间
+    pub start: Duration,
+    /// 结束时间
+    pub end: Duration,
+    /// 去掉格式标签后的文本，多行之间用空格连接
+    pub text: String,
+}
+
+impl Cue {
+    /// 字幕的时长
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// 解析SRT或WebVTT字幕
+///
+/// VTT的注释、样式和区域块会被忽略，文本中的`<i>`、`<v 说话人>`等标签和SRT的`{\an8}`等样式会被去掉。
+pub fn parse_subtitles(content: &str) -> Result<Vec<Cue>, KokoroError> {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().skip_while(|i| !i.contains("-->"));
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+        let (start, end) = timing
+            .split_once("-->")
+            .ok_or_else(|| KokoroError::SubtitleInvalid(timing.to_owned()))?;
+        // VTT的时间之后可以跟随位置等设置
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let text = lines
+            .map(strip_tags)
+            .map(|i| i.trim().to_owned())
+            .filter(|i| !i.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        cues.push(Cue {
+            start: parse_timestamp(start.trim())?,
+            end: parse_timestamp(end)?,
+            text,
+        });
+    }
+    Ok(cues)
+}
+
+/// 解析`hh:mm:ss,mmm`或`mm:ss.mmm`格式的时间，秒的小数部分按位数换算，例如`01,5`为1.5秒
+fn parse_timestamp(timestamp: &str) -> Result<Duration, KokoroError> {
+    let invalid = || KokoroError::SubtitleInvalid(timestamp.to_owned());
+    let (hms, fraction) = timestamp.rsplit_once([',', '.']).ok_or_else(invalid)?;
+    if fraction.is_empty() || fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let nanos =
+        fraction.parse::<u32>().map_err(|_| invalid())? * 10u32.pow(9 - fraction.len() as u32);
+    let mut seconds = 0;
+    for part in hms.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().map_err(|_| invalid())?;
+    }
+    Ok(Duration::new(seconds, nanos))
+}
+
+fn strip_tags(line: &str) -> String {
+    let mut ret = String::with_capacity(line.len());
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => ret.push(c),
+            (Some(end), c) if c == end => closing = None,
+            _ => {}
+        }
+    }
+    ret.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// 导出为SRT字幕
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut ret = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let _ = write!(
+            ret,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            format_timestamp(cue.start, ','),
+            format_timestamp(cue.end, ','),
+            cue.text
+        );
+    }
+    ret
+}
+
+/// 导出为WebVTT字幕
+pub fn to_vtt(cues: &[Cue]) -> String {
+    let mut ret = String::from("WEBVTT\n\n");
+    for cue in cues {
+        let _ = write!(
+            ret,
+            "{} --> {}\n{}\n\n",
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.'),
+            cue.text
+        );
+    }
+    ret
+}
+
+/// 格式化为`hh:mm:ss,mmm`，WebVTT使用`.`分隔毫秒
+fn format_timestamp(time: Duration, separator: char) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// 超出字幕时间段的一条配音
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Overflow {
+    /// 字幕在输入中的序号，从0开始
+    pub index: usize,
+    /// 字幕文本
+    pub text: String,
+    /// 超出字幕结束时间的时长
+    pub overflow: Duration,
+}
+
+/// 根据字幕合成的配音，参见[`crate::KokoroTts::dub`]
+#[derive(Clone, Debug, Default)]
+pub struct Dubbing {
+    /// 连续的音频，字幕之间为静音
+    pub audio: Vec<f32>,
+    /// 压缩到最大语速后仍然超出时间段的字幕
+    pub overflows: Vec<Overflow>,
+}
+
+impl Dubbing {
+    /// 把一条字幕的音频放到时间轴上它的开始时间处，与之前超出的音频重叠时相加
+    pub(super) fn place(&mut self, index: usize, cue: &Cue, audio: &[f32]) {
+        let start = to_samples(cue.start);
+        let end = start + audio.len();
+        if self.audio.len() < end {
+            self.audio.resize(end, 0.);
+        }
+        self.audio[start..end]
+            .iter_mut()
+            .zip(audio)
+            .for_each(|(a, b)| *a += b);
+
+        let slot_end = to_samples(cue.end);
+        // 允许10毫秒的误差
+        if end > slot_end + SAMPLE_RATE as usize / 100 {
+            self.overflows.push(Overflow {
+                index,
+                text: cue.text.clone(),
+                overflow: from_samples(end - slot_end),
+            });
+        }
+    }
+
+    /// 把音轨补齐到指定的时长
+    pub(super) fn pad_to(&mut self, length: Duration) {
+        let len = to_samples(length);
+        if self.audio.len() < len {
+            self.audio.resize(len, 0.);
+        }
+    }
+
+    /// 编码为WAV文件
+    pub fn to_wav(&self, sample_rate: u32) -> Vec<u8> {
+        to_wav(
+            &resample(&self.audio, SAMPLE_RATE, sample_rate),
+            sample_rate,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subtitles() -> Result<(), KokoroError> {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n{\\an8}<i>你好</i>\r\n世界\r\n\r\n2\r\n00:01:00,000 --> 00:01:01,000\r\nTom &amp; Jerry\r\n";
+        let cues = parse_subtitles(srt)?;
+        assert_eq!(2, cues.len());
+        assert_eq!(Duration::from_millis(1000), cues[0].start);
+        assert_eq!(Duration::from_millis(1500), cues[0].duration());
+        assert_eq!("你好 世界", cues[0].text);
+        assert_eq!(Duration::from_secs(60), cues[1].start);
+        assert_eq!("Tom & Jerry", cues[1].text);
+
+        let vtt =
+            "WEBVTT\n\nNOTE 注释\n\nintro\n00:01.000 --> 00:02.000 align:start\n<v 旁白>开始</v>\n";
+        let cues = parse_subtitles(vtt)?;
+        assert_eq!(1, cues.len());
+        assert_eq!(Duration::from_secs(2), cues[0].end);
+        assert_eq!("开始", cues[0].text);
+
+        assert!(parse_subtitles("1\n00:00:01 --> 00:00:02\n你好").is_err());
+
+        // 小数部分按位数换算
+        assert_eq!(Duration::from_millis(1500), parse_timestamp("00:00:01,5")?);
+        assert_eq!(Duration::from_millis(1050), parse_timestamp("00:00:01.05")?);
+        assert!(parse_timestamp("00:00:01,").is_err());
+        assert!(parse_timestamp("00:00:01,+5").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_subtitles() -> Result<(), KokoroError> {
+        let cues = vec![Cue {
+            start: Duration::from_millis(3_723_004),
+            end: Duration::from_millis(3_725_500),
+            text: "你好".to_owned(),
+        }];
+        let srt = to_srt(&cues);
+        assert_eq!("1\n01:02:03,004 --> 01:02:05,500\n你好\n\n", srt);
+        assert_eq!(cues, parse_subtitles(&srt)?);
+        let vtt = to_vtt(&cues);
+        assert!(vtt.starts_with("WEBVTT\n\n01:02:03.004 --> "));
+        assert_eq!(cues, parse_subtitles(&vtt)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dubbing_place() {
+        let cue = |start, end| Cue {
+            start: Duration::from_millis(start),
+            end: Duration::from_millis(end),
+            text: String::new(),
+        };
+        let mut dubbing = Dubbing::default();
+        dubbing.place(0, &cue(500, 1000), &[1.; 12000]);
+        dubbing.place(1, &cue(1000, 1500), &[1.; 24000]);
+        dubbing.pad_to(Duration::from_secs(3));
+        assert_eq!(72000, dubbing.audio.len());
+        assert_eq!(0., dubbing.audio[0]);
+        assert_eq!(1., dubbing.audio[12000]);
+        assert_eq!(
+            vec![1],
+            dubbing
+                .overflows
+                .iter()
+                .map(|i| i.index)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Duration::from_millis(500), dubbing.overflows[0].overflow);
+    }
+}
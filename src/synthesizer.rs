@@ -1,31 +1,91 @@
 use {
-    crate::{KokoroError, Voice, g2p, get_token_ids},
-    ndarray::Array,
-    ort::{
-        inputs,
-        session::{RunOptions, Session},
-        value::TensorRef,
+    crate::{
+        EnglishVariant, G2p, KokoroError, ModelVersion, SAMPLE_RATE, SynthCache, Vocabulary, Voice,
+        model::Model, time_stretch,
     },
+    log::warn,
+    ndarray::Array,
+    ort::{inputs, session::RunOptions, value::TensorRef},
     std::{
         cmp::min,
-        sync::Weak,
+        ops::Range,
+        sync::{Arc, Weak},
         time::{Duration, SystemTime},
     },
-    tokio::sync::Mutex,
 };
 
-async fn synth_v10<'a, P, S>(
-    model: Weak<Mutex<Session>>,
-    phonemes: S,
+/// 合成结果
+pub(super) struct Synthesized {
+    pub(super) audio: Vec<f32>,
+    pub(super) took: Duration,
+    /// 去掉首尾填充符对应的静音后，语音所在的采样范围
+    pub(super) speech: Range<usize>,
+}
+
+impl Synthesized {
+    #[cfg(test)]
+    pub(super) fn new(audio: Vec<f32>, took: Duration) -> Self {
+        Self {
+            speech: 0..audio.len(),
+            audio,
+            took,
+        }
+    }
+
+    /// 时间拉伸，语音范围随之缩放
+    fn stretch(self, rate: f32) -> Self {
+        let audio = time_stretch(&self.audio, rate);
+        let scale =
+            |i: usize| (i as f64 * audio.len() as f64 / self.audio.len().max(1) as f64) as usize;
+        Self {
+            speech: scale(self.speech.start)..scale(self.speech.end),
+            audio,
+            took: self.took,
+        }
+    }
+}
+
+/// 合成的设置，由[`crate::KokoroTts`]的`with_*`方法配置，流式合成时在每个请求中共享
+#[derive(Clone, Default)]
+pub(super) struct Settings {
+    pub(super) cache: Option<Arc<SynthCache>>,
+    pub(super) vocabulary: Option<Arc<Vocabulary>>,
+    /// 为`None`时由发音人决定
+    pub(super) english_variant: Option<EnglishVariant>,
+    /// 词表外无法映射的音素是否导致合成失败
+    pub(super) strict_tokens: bool,
+    /// 模型、音色文件和影响合成结果的设置的指纹，作为缓存键的一部分
+    pub(super) fingerprint: u64,
+}
+
+impl Settings {
+    pub(super) fn new(fingerprint: u64) -> Self {
+        Self {
+            fingerprint,
+            ..Default::default()
+        }
+    }
+}
+
+/// 将音素转换为token id，非严格模式下丢弃词表外无法映射的符号并记录警告
+fn encode(vocabulary: &Vocabulary, phonemes: &str, strict: bool) -> Result<Vec<i64>, KokoroError> {
+    let tokenized = vocabulary.tokenize(phonemes, strict)?;
+    for i in tokenized.dropped() {
+        warn!("Unknown phone {} at {}, skipped.", i.symbol, i.position);
+    }
+    Ok(tokenized.ids)
+}
+
+async fn synth_v10<P>(
+    model: Weak<Model>,
+    phonemes: Vec<i64>,
     pack: P,
     speed: f32,
-) -> Result<(Vec<f32>, Duration), KokoroError>
+) -> Result<Synthesized, KokoroError>
 where
     P: AsRef<Vec<Vec<Vec<f32>>>>,
-    S: AsRef<str>,
 {
     let model = model.upgrade().ok_or(KokoroError::ModelReleased)?;
-    let phonemes = get_token_ids(phonemes.as_ref(), false);
     let phonemes = Array::from_shape_vec((1, phonemes.len()), phonemes)?;
     let ref_s = pack.as_ref()[phonemes.len() - 1]
         .first()
@@ -35,7 +95,7 @@ where
     let style = Array::from_shape_vec((1, ref_s.len()), ref_s)?;
     let speed = Array::from_vec(vec![speed]);
     let options = RunOptions::new()?;
-    let mut model = model.lock().await;
+    let mut model = model.session.lock().await;
     let t = SystemTime::now();
     let kokoro_output = model
         .run_async(
@@ -50,24 +110,45 @@ where
     let elapsed = t.elapsed()?;
     let (_, audio) = kokoro_output["audio"].try_extract_tensor::<f32>()?;
 
-    Ok((audio.to_owned(), elapsed))
+    Ok(Synthesized {
+        speech: voiced_range(audio),
+        audio: audio.to_owned(),
+        took: elapsed,
+    })
 }
 
-async fn synth_v11<P, S>(
-    model: Weak<Mutex<Session>>,
-    phonemes: S,
+/// 根据音量估计语音所在的采样范围，用于不输出时长的1.0模型
+///
+/// 以10毫秒为一帧，均方根低于最响一帧的2%的帧视为静音；整段都是静音时返回整个范围。
+fn voiced_range(audio: &[f32]) -> Range<usize> {
+    const FRAME: usize = SAMPLE_RATE as usize / 100;
+
+    let rms = audio
+        .chunks(FRAME)
+        .map(|i| (i.iter().map(|s| s * s).sum::<f32>() / i.len() as f32).sqrt())
+        .collect::<Vec<_>>();
+    let threshold = rms.iter().copied().fold(0., f32::max) * 0.02;
+    let voiced = |r: &f32| *r > threshold;
+    match (rms.iter().position(voiced), rms.iter().rposition(voiced)) {
+        (Some(first), Some(last)) => first * FRAME..((last + 1) * FRAME).min(audio.len()),
+        _ => 0..audio.len(),
+    }
+}
+
+async fn synth_v11<P>(
+    model: Weak<Model>,
+    mut phonemes: Vec<i64>,
     pack: P,
     speed: i32,
-) -> Result<(Vec<f32>, Duration), KokoroError>
+) -> Result<Synthesized, KokoroError>
 where
     P: AsRef<Vec<Vec<Vec<f32>>>>,
-    S: AsRef<str>,
 {
     let model = model.upgrade().ok_or(KokoroError::ModelReleased)?;
-    let mut phonemes = get_token_ids(phonemes.as_ref(), true);
 
     let mut ret = Vec::new();
     let mut elapsed = Duration::ZERO;
+    let mut chunks = Vec::new();
     while let p = phonemes.drain(..min(pack.as_ref().len(), phonemes.len()))
         && p.len() != 0
     {
@@ -80,7 +161,7 @@ where
         let style = Array::from_shape_vec((1, ref_s.len()), ref_s)?;
         let speed = Array::from_vec(vec![speed]);
         let options = RunOptions::new()?;
-        let mut model = model.lock().await;
+        let mut model = model.session.lock().await;
         let t = SystemTime::now();
         let kokoro_output = model
             .run_async(
@@ -94,30 +175,151 @@ where
             .await?;
         elapsed = t.elapsed()?;
         let (_, audio) = kokoro_output["waveform"].try_extract_tensor::<f32>()?;
-        let (_, _duration) = kokoro_output["duration"].try_extract_tensor::<i64>()?;
-        // let _ = dbg!(duration.len());
+        let (_, duration) = kokoro_output["duration"].try_extract_tensor::<i64>()?;
+        chunks.push(edge_samples(audio.len(), duration));
         ret.extend_from_slice(audio);
     }
 
-    Ok((ret, elapsed))
+    Ok(Synthesized {
+        speech: speech_range(ret.len(), &chunks),
+        audio: ret,
+        took: elapsed,
+    })
+}
+
+/// 一段模型输出中首尾填充符对应的静音采样数
+///
+/// 模型输出每个token的时长单位，按音频长度换算成采样数。
+fn edge_samples(audio_len: usize, duration: &[i64]) -> (usize, usize) {
+    let scale = audio_len as f64 / duration.iter().sum::<i64>().max(1) as f64;
+    let samples = |d: Option<&i64>| (d.copied().unwrap_or_default().max(0) as f64 * scale) as usize;
+    (samples(duration.first()), samples(duration.last()))
+}
+
+/// 多段输出拼接后语音所在的采样范围，只去掉第一段开头和最后一段结尾的静音
+fn speech_range(len: usize, chunks: &[(usize, usize)]) -> Range<usize> {
+    let leading = chunks.first().map_or(0, |i| i.0).min(len);
+    let trailing = chunks.last().map_or(0, |i| i.1);
+    leading..len.saturating_sub(trailing).max(leading)
 }
 
 pub(super) async fn synth<'a, P, S>(
-    model: Weak<Mutex<Session>>,
+    model: Weak<Model>,
+    g2p: &G2p,
     text: S,
     pack: P,
     voice: Voice,
-) -> Result<(Vec<f32>, Duration), KokoroError>
+    settings: &Settings,
+) -> Result<Synthesized, KokoroError>
 where
     P: AsRef<Vec<Vec<Vec<f32>>>>,
     S: AsRef<str>,
 {
-    let phonemes = g2p(text.as_ref(), voice.is_v11_supported())?;
+    let version = model.upgrade().ok_or(KokoroError::ModelReleased)?.version;
+    let text = text.as_ref();
+    let (cache, vocabulary) = (settings.cache.as_deref(), settings.vocabulary.as_deref());
+    if let Some(cache) = cache
+        && let Some((audio, speech)) = cache.audio(settings.fingerprint, text, voice)
+    {
+        return Ok(Synthesized {
+            audio,
+            took: Duration::ZERO,
+            speech,
+        });
+    }
+
+    let v11 = version == ModelVersion::V11;
+    let variant = settings
+        .english_variant
+        .unwrap_or_else(|| voice.english_variant());
+    let phonemes = match cache.and_then(|c| c.phonemes(settings.fingerprint, text, v11, variant)) {
+        Some(phonemes) => phonemes,
+        None => {
+            let phonemes = g2p.g2p(text, v11, variant)?;
+            if let Some(cache) = cache {
+                cache.insert_phonemes(settings.fingerprint, text, v11, variant, &phonemes);
+            }
+            phonemes
+        }
+    };
     // #[cfg(debug_assertions)]
     // println!("{}", phonemes);
-    match voice {
-        v if v.is_v11_supported() => synth_v11(model, phonemes, pack, v.get_speed_v11()?).await,
-        v if v.is_v10_supported() => synth_v10(model, phonemes, pack, v.get_speed_v10()?).await,
-        v => Err(KokoroError::VoiceVersionInvalid(v.get_name().to_owned())),
+    let vocabulary = vocabulary.unwrap_or(match version {
+        ModelVersion::V11 => Vocabulary::v11(),
+        ModelVersion::V10 => Vocabulary::v10(),
+    });
+    let tokens = encode(vocabulary, &phonemes, settings.strict_tokens)?;
+    let synthesized = match version {
+        ModelVersion::V11 => {
+            // 模型只支持整数语速，小数部分通过时间拉伸实现
+            let speed = voice.get_speed_v11()?;
+            let model_speed = speed.floor().max(1.);
+            synth_v11(model, tokens, pack, model_speed as i32)
+                .await
+                .map(|s| s.stretch(speed / model_speed))
+        }
+        ModelVersion::V10 => synth_v10(model, tokens, pack, voice.get_speed_v10()?).await,
+    }?;
+    if let Some(cache) = cache {
+        cache.insert_audio(
+            settings.fingerprint,
+            text,
+            voice,
+            &synthesized.audio,
+            synthesized.speech.clone(),
+        );
+    }
+    Ok(synthesized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let vocabulary = Vocabulary::v11();
+        // 非严格模式下词表外的符号被替换或丢弃
+        let ids = encode(vocabulary, "ɡɚʘ", false).unwrap();
+        assert_eq!("ɡəɹ", vocabulary.decode(&ids));
+        assert!(matches!(
+            encode(vocabulary, "ɡɚʘ", true),
+            Err(KokoroError::UnknownPhoneme('ʘ', 2))
+        ));
+    }
+
+    #[test]
+    fn test_speech_range() {
+        // 时长单位之和为10，每个单位对应100个采样
+        assert_eq!((200, 100), edge_samples(1000, &[2, 3, 4, 1]));
+        assert_eq!((0, 0), edge_samples(1000, &[]));
+        assert_eq!(
+            200..900,
+            speech_range(1000, &[edge_samples(1000, &[2, 3, 4, 1])])
+        );
+
+        // 多段拼接时中间段的首尾静音属于语音内部
+        let chunks = [
+            edge_samples(1000, &[2, 3, 4, 1]),
+            edge_samples(500, &[1, 3, 1]),
+            edge_samples(600, &[1, 4, 1]),
+        ];
+        assert_eq!(200..2000, speech_range(2100, &chunks));
+
+        // 静音比音频还长时范围为空
+        assert_eq!(100..100, speech_range(100, &[(150, 150)]));
+        assert_eq!(0..0, speech_range(0, &[]));
+    }
+
+    #[test]
+    fn test_voiced_range() {
+        // 0.1秒静音、0.5秒正弦波、0.2秒微弱的噪声
+        let mut audio = vec![0.; 2400];
+        audio.extend((0..12000).map(|i| (i as f32 * 0.1).sin() * 0.5));
+        audio.extend((0..4800).map(|i| if i % 2 == 0 { 1e-3 } else { -1e-3 }));
+        assert_eq!(2400..14400, voiced_range(&audio));
+
+        assert_eq!(0..1000, voiced_range(&[0.; 1000]));
+        assert_eq!(0..0, voiced_range(&[]));
     }
 }
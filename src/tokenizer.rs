@@ -1,9 +1,16 @@
 use {
+    crate::KokoroError,
     log::warn,
-    std::{collections::HashMap, sync::LazyLock},
+    serde_json::Value,
+    std::{
+        collections::{BTreeMap, HashMap},
+        fs::read_to_string,
+        path::Path,
+        sync::LazyLock,
+    },
 };
 
-const VOCAB_V10: LazyLock<HashMap<char, u8>> = LazyLock::new(|| {
+static VOCAB_V10: LazyLock<Vocabulary> = LazyLock::new(|| {
     let mut map = HashMap::new();
 
     map.insert(';', 1);
@@ -120,10 +127,10 @@ const VOCAB_V10: LazyLock<HashMap<char, u8>> = LazyLock::new(|| {
     map.insert('↗', 172);
     map.insert('↘', 173);
     map.insert('ᵻ', 177);
-    map
+    Vocabulary::new(map)
 });
 
-const VOCAB_V11: LazyLock<HashMap<char, u8>> = LazyLock::new(|| {
+static VOCAB_V11: LazyLock<Vocabulary> = LazyLock::new(|| {
     let mut map = HashMap::new();
 
     map.insert(';', 1);
@@ -297,29 +304,264 @@ const VOCAB_V11: LazyLock<HashMap<char, u8>> = LazyLock::new(|| {
     map.insert('元', 175);
     map.insert('云', 176);
     map.insert('ᵻ', 177);
-    map
+    Vocabulary::new(map)
 });
 
-pub fn get_token_ids(phonemes: &str, v11: bool) -> Vec<i64> {
-    let mut tokens = Vec::with_capacity(phonemes.len() + 2);
-    tokens.push(0);
-
-    for i in phonemes.chars() {
-        let v = if v11 {
-            VOCAB_V11.get(&i).map(|i| *i)
-        } else {
-            VOCAB_V10.get(&i).map(|i| *i)
+/// 常见的词表外IPA符号到最接近的受支持音素的映射，替换结果为空表示直接去掉该符号
+const NEAREST: [(char, &str); 27] = [
+    ('g', "ɡ"),
+    ('ɝ', "ɜɹ"),
+    ('ɚ', "əɹ"),
+    ('ɻ', "ɹ"),
+    ('ʀ', "ʁ"),
+    ('ɫ', "l"),
+    ('ɬ', "l"),
+    ('ɭ', "l"),
+    ('ɘ', "ə"),
+    ('ɵ', "ə"),
+    ('ɞ', "ɜ"),
+    ('ʉ', "u"),
+    ('ʏ', "y"),
+    ('ɦ', "h"),
+    ('ʍ', "w"),
+    ('ʷ', "w"),
+    ('ɱ', "m"),
+    ('ʐ', "ʒ"),
+    ('ɧ', "ʃ"),
+    ('\'', "ˈ"),
+    ('\t', " "),
+    ('\n', " "),
+    ('\u{00A0}', " "),
+    ('\u{0361}', ""), // 连接符，例如t͡ʃ
+    ('\u{0329}', ""), // 成音节符
+    ('\u{032F}', ""), // 不成音节符
+    ('\u{200D}', ""),
+];
+
+/// 分词时被替换或丢弃的符号
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownPhoneme {
+    /// 符号在音素字符串中的位置（按字符计）
+    pub position: usize,
+    /// 词表中没有的符号
+    pub symbol: char,
+    /// 替换成的音素，`None`表示该符号被丢弃
+    pub replacement: Option<String>,
+}
+
+/// 分词结果
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tokenized {
+    /// 包括首尾填充符在内的token id
+    pub ids: Vec<i64>,
+    /// 所有词表外的符号
+    pub unknown: Vec<UnknownPhoneme>,
+}
+
+impl Tokenized {
+    /// 被丢弃的符号
+    pub fn dropped(&self) -> impl Iterator<Item = &UnknownPhoneme> {
+        self.unknown.iter().filter(|i| i.replacement.is_none())
+    }
+}
+
+/// 模型的音素词表
+///
+/// 内置了v1.0和v1.1模型的词表，也可以从模型的config.json中加载，以支持词表不同的新模型。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Vocabulary {
+    ids: HashMap<char, i64>,
+    symbols: BTreeMap<i64, char>,
+}
+
+impl Vocabulary {
+    fn get(v11: bool) -> &'static Self {
+        if v11 { Self::v11() } else { Self::v10() }
+    }
+
+    /// 从音素到token id的映射创建词表
+    pub fn new(ids: HashMap<char, i64>) -> Self {
+        let symbols = ids.iter().map(|(c, i)| (*i, *c)).collect();
+        Self { ids, symbols }
+    }
+
+    /// v1.0模型的词表
+    pub fn v10() -> &'static Self {
+        &VOCAB_V10
+    }
+
+    /// v1.1模型的词表
+    pub fn v11() -> &'static Self {
+        &VOCAB_V11
+    }
+
+    /// 从模型的config.json加载词表
+    ///
+    /// # 参数
+    ///
+    /// * `json` - 配置文件的内容，词表位于其中的`vocab`字段。
+    pub fn from_config_json(json: &str) -> Result<Self, KokoroError> {
+        let config: Value = serde_json::from_str(json)?;
+        let vocab = config
+            .get("vocab")
+            .and_then(Value::as_object)
+            .ok_or_else(|| KokoroError::VocabularyInvalid("Missing vocab".to_owned()))?;
+
+        let mut ids = HashMap::with_capacity(vocab.len());
+        for (symbol, id) in vocab {
+            let mut chars = symbol.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return Err(KokoroError::VocabularyInvalid(format!(
+                    "Symbol {:?} is not a single character",
+                    symbol
+                )));
+            };
+            let Some(id) = id.as_i64() else {
+                return Err(KokoroError::VocabularyInvalid(format!(
+                    "Id of {:?} is not an integer",
+                    symbol
+                )));
+            };
+            ids.insert(c, id);
+        }
+        Ok(Self::new(ids))
+    }
+
+    /// 从模型的config.json文件加载词表
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self, KokoroError> {
+        Self::from_config_json(&read_to_string(path)?)
+    }
+
+    /// 按token id顺序列出所有音素
+    pub fn symbols(&self) -> impl Iterator<Item = (char, i64)> + '_ {
+        self.symbols.iter().map(|(i, c)| (*c, *i))
+    }
+
+    /// 词表中的音素个数
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// 词表是否为空
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// 获取音素的token id
+    pub fn id(&self, symbol: char) -> Option<i64> {
+        self.ids.get(&symbol).copied()
+    }
+
+    /// 获取token id对应的音素
+    pub fn symbol(&self, id: i64) -> Option<char> {
+        self.symbols.get(&id).copied()
+    }
+
+    /// 音素字符串是否可以完全用该词表表示
+    pub fn is_representable(&self, phonemes: &str) -> bool {
+        phonemes.chars().all(|c| self.ids.contains_key(&c))
+    }
+
+    /// 查找词表外符号最接近的受支持音素
+    ///
+    /// Return: 替换后的音素，可能为空字符串；没有合适的音素时返回`None`
+    pub fn nearest(&self, symbol: char) -> Option<&'static str> {
+        NEAREST
+            .iter()
+            .find(|(c, _)| *c == symbol)
+            .map(|(_, r)| *r)
+            .filter(|r| self.is_representable(r))
+    }
+
+    /// 将音素转换为token id，并报告词表外的符号
+    ///
+    /// 词表外的符号会先尝试映射到最接近的受支持音素，无法映射的符号会被丢弃。
+    ///
+    /// # 参数
+    ///
+    /// * `phonemes` - 音素字符串。
+    /// * `strict` - 严格模式，存在无法映射的符号时返回错误。
+    pub fn tokenize(&self, phonemes: &str, strict: bool) -> Result<Tokenized, KokoroError> {
+        let mut ret = Tokenized {
+            ids: Vec::with_capacity(phonemes.len() + 2),
+            unknown: Vec::new(),
         };
-        match v {
-            Some(t) => {
-                tokens.push(t as _);
+        ret.ids.push(0);
+
+        for (position, i) in phonemes.chars().enumerate() {
+            if let Some(t) = self.id(i) {
+                ret.ids.push(t);
+                continue;
             }
-            _ => {
-                warn!("Unknown phone {}, skipped.", i);
+            let replacement = self.nearest(i);
+            match replacement {
+                Some(r) => ret.ids.extend(r.chars().filter_map(|c| self.id(c))),
+                None if strict => return Err(KokoroError::UnknownPhoneme(i, position)),
+                None => {}
             }
+            ret.unknown.push(UnknownPhoneme {
+                position,
+                symbol: i,
+                replacement: replacement.map(ToOwned::to_owned),
+            });
         }
+
+        ret.ids.push(0);
+        Ok(ret)
     }
 
-    tokens.push(0);
-    tokens
+    /// 将音素转换为token id，首尾会加上填充符，词表外的符号会被替换或丢弃
+    pub fn encode(&self, phonemes: &str) -> Vec<i64> {
+        let tokenized = self.tokenize(phonemes, false).unwrap_or_default();
+        for i in tokenized.dropped() {
+            warn!("Unknown phone {} at {}, skipped.", i.symbol, i.position);
+        }
+        tokenized.ids
+    }
+
+    /// 将token id转换回音素，填充符和未知的id会被忽略
+    pub fn decode(&self, ids: &[i64]) -> String {
+        ids.iter().filter_map(|i| self.symbol(*i)).collect()
+    }
+}
+
+pub fn get_token_ids(phonemes: &str, v11: bool) -> Vec<i64> {
+    Vocabulary::get(v11).encode(phonemes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vocabulary() -> anyhow::Result<()> {
+        let vocab =
+            Vocabulary::from_config_json(r#"{"n_token": 4, "vocab": {"a": 1, "b": 2, "ɹ": 3}}"#)?;
+        assert_eq!(
+            vec![('a', 1), ('b', 2), ('ɹ', 3)],
+            vocab.symbols().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0, 1, 3, 2, 0], vocab.encode("aɹb"));
+        assert_eq!("aɹb", vocab.decode(&[0, 1, 3, 2, 0]));
+        assert!(vocab.is_representable("ab"));
+        assert!(!vocab.is_representable("abc"));
+        assert!(Vocabulary::from_config_json(r#"{"vocab": {"ab": 1}}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokenize() -> anyhow::Result<()> {
+        assert_eq!(Some("ɡ"), Vocabulary::v11().nearest('g'));
+        assert_eq!(None, Vocabulary::v11().nearest('ʘ'));
+
+        let tokenized = Vocabulary::v11().tokenize("ɡɚʘ", false)?;
+        assert_eq!(Some("əɹ".to_owned()), tokenized.unknown[0].replacement);
+        assert_eq!(None, tokenized.unknown[1].replacement);
+        assert_eq!(1, tokenized.dropped().count());
+        assert!(matches!(
+            Vocabulary::v11().tokenize("ɡɚ!ʘ", true),
+            Err(KokoroError::UnknownPhoneme('ʘ', 3))
+        ));
+        Ok(())
+    }
 }
@@ -102,13 +102,14 @@ static ARPA_IPA_MAP: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::n
     map
 });
 
+static ARPA_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([A-Z!]+)(\d*)").expect("invalid ARPAbet pattern"));
+
 /// 支持2025新增符号（如：吸气音ʘ）
 const SPECIAL_CASES: [(&str, &str); 3] = [("CLICK!", "ʘ"), ("TSK!", "ǀ"), ("TUT!", "ǁ")];
 
 pub fn arpa_to_ipa(arpa: &str) -> Result<String, regex::Error> {
-    let re = Regex::new(r"([A-Z!]+)(\d*)")?;
-
-    let Some(caps) = re.captures(arpa) else {
+    let Some(caps) = ARPA_PATTERN.captures(arpa) else {
         return Ok(Default::default());
     };
 
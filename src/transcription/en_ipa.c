@@ -62,17 +62,23 @@
 #define phonSYLLABIC    20
 #define REPLACED_E       'E' // 'e' replaced by silent e
 #define RULE_CONDITION   5 // followed by condition number (byte)
+#define RULE_DEC_SCORE   13 // <
 #define RULE_DOUBLE       11 // %
+#define RULE_INC_SCORE   12 // +
 #define RULE_GROUP_END   7
 #define RULE_GROUP_START 6
 #define RULE_LETTERGP     17 // A B C H F G Y   letter group number
 #define RULE_LINENUM     9 // next 2 bytes give a line number, for debugging purposes
 #define RULE_NO_SUFFIX    24 // N
 #define RULE_NOTVOWEL     25 // K
+#define RULE_NOVOWELS    29 // X no vowels up to word boundary
 #define RULE_PH_COMMON   4 // At start of rule. Its phoneme string is used by subsequent rules
 #define RULE_PHONEMES    3
 #define RULE_POST        2
 #define RULE_PRE         1
+#define RULE_PRE_ATSTART 8 // pre rule with implied 'start of word'
+#define RULE_STRESSED    10 // &
+#define RULE_SYLLABLE    21 // @
 #define SFLAG_EMBEDDED         0x02 // there are embedded commands before this phoneme
 #define SFLAG_SYLLABLE         0x04 // vowel or syllabic consonant
 #define STRESS_IS_DIMINISHED    0       // diminished, unstressed within a word
@@ -132,6 +138,10 @@ typedef struct {
 
 static const char *data_dictrules;     // language_1   translation rules file
 static int dict_condition;    // conditional apply some pronunciation rules and dict.lookups
+static int word_vowel_count;    // number of vowel phonemes so far in the word being translated
+static int word_stressed_count; // number of stressable vowel phonemes so far
+static int american;          // write phonemes with General American (rhotic) values
+static int full_rules;        // also match the rule types which are only used for words missing from cmudict
 static const char *dict_hashtab[N_HASH_DICT];   // hash table to index dictionary lookup file
 static const char *groups1[256];         // translation rule lists, index by single letter
 static const char *groups2[N_RULE_GROUP2];   // translation rule lists, indexed by two-letter pairs
@@ -2094,6 +2104,13 @@ static void MatchRule(char *word[], int group_length, const char *rule, MatchRec
 						failed = 1;
 					}
 					break;
+				case RULE_PRE_ATSTART: // pre rule with implied 'start of word'
+					if (!full_rules)
+						break;
+					check_atstart = true;
+					unpron_ignore = 0;
+					match_type = RULE_PRE;
+					break;
 				case RULE_POST:
 					match_type = RULE_POST;
 					break;
@@ -2183,7 +2200,63 @@ static void MatchRule(char *word[], int group_length, const char *rule, MatchRec
 					post_ptr--;
 					add_points = 1;
 					break;
+				case RULE_SYLLABLE:
+				{
+					// more than specified number of vowel letters to the right
+					if (!full_rules)
+						goto post_letter;
+					char *p_next = post_ptr + letter_xbytes;
+					int vowel = 0;
+					int vowel_count = 0;
+					int syllable_count = 1;
+					while (*rule == RULE_SYLLABLE) {
+						rule++;
+						syllable_count++; // more than one syllable
+					}
+					while ((letter_w != ' ') && (letter_w != 0)) {
+						if ((vowel == 0) && IsLetter(letter_w, LETTERGP_VOWEL2)) {
+							// count vowels which are separated by non-vowel letters
+							vowel_count++;
+						}
+						vowel = IsLetter(letter_w, LETTERGP_VOWEL2);
+						p_next += utf8_in2(&letter_w, p_next);
+					}
+					if (syllable_count <= vowel_count)
+						add_points = (18+syllable_count-distance_right);
+					else
+						failed = 1;
+					break;
+				}
+				case RULE_NOVOWELS:
+				{
+					if (!full_rules)
+						goto post_letter;
+					char *p_next = post_ptr + letter_xbytes;
+					while ((letter_w != ' ') && (letter_w != 0)) {
+						if (IsLetter(letter_w, LETTERGP_VOWEL2)) {
+							failed = 1;
+							break;
+						}
+						p_next += utf8_in2(&letter_w, p_next);
+					}
+					if (!failed)
+						add_points = (19-distance_right);
+					break;
+				}
+				case RULE_INC_SCORE:
+					if (!full_rules)
+						goto post_letter;
+					post_ptr--;
+					add_points = 20; // force an increase in points
+					break;
+				case RULE_DEC_SCORE:
+					if (!full_rules)
+						goto post_letter;
+					post_ptr--;
+					add_points = -20; // force an decrease in points
+					break;
 				default:
+				post_letter:
 					if (letter == rb) {
 						if ((letter & 0xc0) != 0x80) {
 							// not for non-initial UTF-8 bytes
@@ -2234,6 +2307,30 @@ static void MatchRule(char *word[], int group_length, const char *rule, MatchRec
 					} else
 						failed = 1;
 					break;
+				case RULE_SYLLABLE:
+				{
+					// more than specified number of vowels to the left
+					if (!full_rules)
+						goto pre_letter;
+					int syllable_count = 1;
+					while (*rule == RULE_SYLLABLE) {
+						rule++;
+						syllable_count++; // more than one syllable
+					}
+					if (syllable_count <= word_vowel_count)
+						add_points = (18+syllable_count-distance_left);
+					else
+						failed = 1;
+					break;
+				}
+				case RULE_STRESSED:
+					if (!full_rules)
+						goto pre_letter;
+					if (word_stressed_count > 0)
+						add_points = 19;
+					else
+						failed = 1;
+					break;
 				case '.':
 					// dot in pre- section, match on any dot before this point in the word
 					for (p = pre_ptr; *p != ' '; p--) {
@@ -2249,6 +2346,7 @@ static void MatchRule(char *word[], int group_length, const char *rule, MatchRec
 					failed = 1;
 					break;
 				default:
+				pre_letter:
 					if (letter == rb) {
 						if ((letter & 0xc0) != 0x80) {
 							// not for non-initial UTF-8 bytes
@@ -2315,8 +2413,10 @@ static void AppendPhonemes(char *string, int size, const char *ph) {
 			if (phoneme_tab[c].type == phVOWEL) {
 				if (((phoneme_tab[c].phflags & phUNSTRESSED) == 0) &&
 				    (unstress_mark == false)) {
+					word_stressed_count++;
 				}
 				unstress_mark = false;
+				word_vowel_count++;
 			}
 		}
 	}
@@ -2352,6 +2452,8 @@ static int TranslateRules(char *p_start, char *phonemes, int ph_size, char *end_
 	}
 	word_copy[ix] = 0;
 	p = p_start;
+	word_vowel_count = 0;
+	word_stressed_count = 0;
 	if (end_phonemes != NULL)
 		end_phonemes[0] = 0;
 
@@ -3202,6 +3304,48 @@ static void InterpretPhoneme2(int phcode, PHONEME_DATA *phdata) {
 	InterpretPhoneme(&plist[1], phdata, NULL);
 }
 
+// General American values for phonemes whose en (British) names are non-rhotic or use British vowels,
+// following the en-us phoneme table and misaki's American phoneme set.
+static const char *american_ipa[][2] = {
+	{ "3:", "ɜɹ" },
+	{ "3", "əɹ" },
+	{ "A@", "ɑɹ" },
+	{ "O@", "ɔɹ" },
+	{ "o@", "ɔɹ" },
+	{ "e@", "ɛɹ" },
+	{ "i@", "ɪɹ" },
+	{ "i@3", "ɪɹ" },
+	{ "U@", "ʊɹ" },
+	{ "aI@", "aɪəɹ" },
+	{ "aI3", "aɪəɹ" },
+	{ "aU@", "aʊəɹ" },
+	{ "IR", "ɪɹ" },
+	{ "VR", "ʌɹ" },
+	{ "r-", "ɹ" },
+	{ "oU", "oʊ" },
+	{ "0", "ɑ" },
+	{ "a", "æ" },
+	{ "aa", "æ" },
+	{ "A:", "ɑ" },
+	{ "O:", "ɔ" },
+	{ "i:", "i" },
+	{ "u:", "u" },
+	{ NULL, NULL }
+};
+
+static const char *LookupAmericanIpa(unsigned int mnemonic) {
+	char name[5];
+	int ix = 0;
+	for (; (mnemonic & 0xff) != 0 && ix < 4; mnemonic >>= 8)
+		name[ix++] = (char) (mnemonic & 0xff);
+	name[ix] = 0;
+	for (ix = 0; american_ipa[ix][0] != NULL; ix++) {
+		if (strcmp(american_ipa[ix][0], name) == 0)
+			return american_ipa[ix][1];
+	}
+	return NULL;
+}
+
 static char *WritePhMnemonic(char *phon_out, PHONEME_TAB *ph, PHONEME_LIST *plist, int *flags) {
 	int c;
 	int mnem;
@@ -3224,6 +3368,17 @@ static char *WritePhMnemonic(char *phon_out, PHONEME_TAB *ph, PHONEME_LIST *plis
 		*phon_out = 0;
 		return phon_out;
 	}
+	if (american && (p = (char *)LookupAmericanIpa(ph->mnemonic)) != NULL) {
+		len = (int) strlen(p);
+		if ((plist != NULL) && (len > 2) && (strcmp(&p[len-2], "\xc9\xb9") == 0) &&
+		    ((plist[1].ph->mnemonic & 0xff) == 'r'))
+			len -= 2; // the linking r which follows already gives the r-colouring
+		memcpy(phon_out, p, len);
+		phon_out += len;
+		*phon_out = 0;
+		return phon_out;
+	}
+	p = phdata.ipa_string;
 	if ((*p != 0) && ((*p & 0xff) < 0x20)) {
 		// name starts with a flags byte
 		if (flags != NULL)
@@ -3250,6 +3405,8 @@ static char *WritePhMnemonic(char *phon_out, PHONEME_TAB *ph, PHONEME_LIST *plis
 			break; // # is subscript-h, but only for consonants
 		if ((c >= 0x20) && (c < 128))
 			c = ipa1[c-0x20];
+		if (full_rules && (c >= '0') && (c <= '9'))
+			continue; // a digit which remains is a phoneme variant indicator
 
 		ix += utf8_out(c, &phon_out[ix]);
 		first = false;
@@ -3425,6 +3582,16 @@ void Initialize(const char *data_dictlist) {
 	}
 }
 
+void SetFullRules(int enable) {
+	full_rules = enable;
+}
+
+void SetAmerican(int enable) {
+	american = enable;
+	// the en-us voice selects rule conditions 3 and 6
+	dict_condition = enable ? ((1 << 3) | (1 << 6)) : 0;
+}
+
 const char *TextToPhonemes(const char *textptr) {
 	int ix;
 	int c;
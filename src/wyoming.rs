@@ -0,0 +1,359 @@
+use {
+    crate::{
+        KokoroError, KokoroTts, SAMPLE_RATE, SynthSink, SynthStream, Voice, split_sentences,
+        to_pcm_s16le,
+    },
+    futures::StreamExt,
+    log::warn,
+    serde_json::{Map, Value, json},
+    std::{
+        io::{Error as IoError, ErrorKind},
+        sync::Arc,
+    },
+    tokio::{
+        io::{
+            AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, split,
+        },
+        net::TcpListener,
+    },
+};
+
+const PROTOCOL_VERSION: &str = "1.5.4";
+/// 事件头一行JSON的最大长度
+const MAX_HEADER_LENGTH: u64 = 64 << 10;
+/// JSON数据和二进制负载各自的最大长度，避免客户端让服务端分配任意大的内存
+const MAX_BODY_LENGTH: usize = 4 << 20;
+
+/// Wyoming协议的事件：一行JSON头，之后是可选的JSON数据和二进制负载
+struct Event {
+    event_type: String,
+    data: Map<String, Value>,
+    payload: Vec<u8>,
+}
+
+impl Event {
+    fn new(event_type: &str, data: Value) -> Self {
+        Self {
+            event_type: event_type.to_owned(),
+            data: match data {
+                Value::Object(map) => map,
+                _ => Map::new(),
+            },
+            payload: Vec::new(),
+        }
+    }
+
+    async fn read<R: AsyncRead + Unpin>(
+        reader: &mut BufReader<R>,
+    ) -> Result<Option<Self>, KokoroError> {
+        let invalid = |msg: String| IoError::new(ErrorKind::InvalidData, msg);
+        let mut line = String::new();
+        let read = (&mut *reader)
+            .take(MAX_HEADER_LENGTH)
+            .read_line(&mut line)
+            .await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read as u64 == MAX_HEADER_LENGTH && !line.ends_with('\n') {
+            return Err(
+                invalid(format!("Event header exceeds {} bytes", MAX_HEADER_LENGTH)).into(),
+            );
+        }
+        let header: Value = serde_json::from_str(&line)?;
+        let length = |key| {
+            let length = header.get(key).and_then(Value::as_u64).unwrap_or(0);
+            match usize::try_from(length) {
+                Ok(length) if length <= MAX_BODY_LENGTH => Ok(length),
+                _ => Err(invalid(format!(
+                    "{} {} exceeds {} bytes",
+                    key, length, MAX_BODY_LENGTH
+                ))),
+            }
+        };
+
+        let mut data = match header.get("data") {
+            Some(Value::Object(map)) => map.to_owned(),
+            _ => Map::new(),
+        };
+        let data_length = length("data_length")?;
+        if data_length > 0 {
+            let mut buf = vec![0; data_length];
+            reader.read_exact(&mut buf).await?;
+            if let Value::Object(map) = serde_json::from_slice(&buf)? {
+                data.extend(map);
+            }
+        }
+        let mut payload = vec![0; length("payload_length")?];
+        reader.read_exact(&mut payload).await?;
+
+        Ok(Some(Self {
+            event_type: header
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            data,
+            payload,
+        }))
+    }
+
+    async fn write<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<(), KokoroError> {
+        let data = serde_json::to_vec(&self.data)?;
+        let mut header = json!({
+            "type": self.event_type,
+            "version": PROTOCOL_VERSION,
+            "data_length": data.len(),
+        });
+        if !self.payload.is_empty() {
+            header["payload_length"] = self.payload.len().into();
+        }
+        let mut buf = serde_json::to_vec(&header)?;
+        buf.push(b'\n');
+        buf.extend(data);
+        buf.extend_from_slice(&self.payload);
+        writer.write_all(&buf).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+fn voice_language(name: &str) -> &'static str {
+    Voice::from_name(name, 1.)
+        .map(|v| v.language())
+        .unwrap_or_default()
+}
+
+fn info(voices: &[String]) -> Event {
+    let attribution = json!({
+        "name": "hexgrad",
+        "url": "https://github.com/hexgrad/kokoro",
+    });
+    let voices = voices
+        .iter()
+        .map(|name| {
+            json!({
+                "name": name,
+                "description": name,
+                "attribution": attribution,
+                "installed": true,
+                "version": null,
+                "languages": [voice_language(name)],
+                "speakers": null,
+            })
+        })
+        .collect::<Vec<_>>();
+    Event::new(
+        "info",
+        json!({
+            "asr": [],
+            "tts": [{
+                "name": "kokoro",
+                "description": "Kokoro TTS",
+                "attribution": attribution,
+                "installed": true,
+                "version": env!("CARGO_PKG_VERSION"),
+                "voices": voices,
+            }],
+            "handle": [],
+            "intent": [],
+            "wake": [],
+        }),
+    )
+}
+
+/// 按名称或语言选择发音人，都没有指定时使用第一个发音人
+fn choose_voice(voices: &[String], data: &Map<String, Value>) -> Option<String> {
+    let voice = data.get("voice");
+    let field = |key| voice.and_then(|v| v.get(key)).and_then(Value::as_str);
+    if let Some(name) = field("name") {
+        return voices.iter().find(|i| *i == name).cloned();
+    }
+    match field("language") {
+        Some(language) => voices
+            .iter()
+            .find(|i| voice_language(i).eq_ignore_ascii_case(language))
+            .or_else(|| {
+                voices.iter().find(|i| {
+                    let lang = voice_language(i);
+                    !lang.is_empty() && language.starts_with(&lang[..2])
+                })
+            })
+            .cloned(),
+        None => voices.first().cloned(),
+    }
+}
+
+fn audio_format() -> Value {
+    json!({
+        "rate": SAMPLE_RATE,
+        "width": 2,
+        "channels": 1,
+    })
+}
+
+async fn synthesize<W, F>(
+    writer: &mut W,
+    voices: &[String],
+    data: &Map<String, Value>,
+    stream: &F,
+) -> Result<(), KokoroError>
+where
+    W: AsyncWrite + Unpin,
+    F: Fn(Voice) -> (SynthSink<String>, SynthStream),
+{
+    let text = data.get("text").and_then(Value::as_str).unwrap_or_default();
+    let voice = choose_voice(voices, data)
+        .ok_or_else(|| KokoroError::VoiceNotFound(format!("{:?}", data.get("voice"))))
+        .and_then(|name| Voice::from_name(&name, 1.))?;
+
+    let (mut sink, mut audio) = stream(voice);
+    for sentence in split_sentences(text) {
+        sink.synth(sentence).await?;
+    }
+    drop(sink);
+
+    Event::new("audio-start", audio_format())
+        .write(writer)
+        .await?;
+    while let Some((data, _)) = audio.next().await {
+        let mut chunk = Event::new("audio-chunk", audio_format());
+        chunk.payload = to_pcm_s16le(&data);
+        chunk.write(writer).await?;
+    }
+    if let Some(e) = audio.take_error() {
+        return Err(e);
+    }
+    Event::new("audio-stop", json!({})).write(writer).await
+}
+
+async fn handle_connection<S, F>(
+    connection: S,
+    voices: Vec<String>,
+    stream: F,
+) -> Result<(), KokoroError>
+where
+    S: AsyncRead + AsyncWrite,
+    F: Fn(Voice) -> (SynthSink<String>, SynthStream),
+{
+    let (reader, mut writer) = split(connection);
+    let mut reader = BufReader::new(reader);
+    while let Some(event) = Event::read(&mut reader).await? {
+        match event.event_type.as_str() {
+            "describe" => info(&voices).write(&mut writer).await?,
+            "synthesize" => {
+                if let Err(e) = synthesize(&mut writer, &voices, &event.data, &stream).await {
+                    Event::new("error", json!({ "text": e.to_string() }))
+                        .write(&mut writer)
+                        .await?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 启动Wyoming协议的语音合成服务
+///
+/// Home Assistant等客户端可以通过`describe`事件获取所有发音人及其语言，通过`synthesize`事件合成语音，
+/// 音频以`audio-start`、`audio-chunk`和`audio-stop`事件逐句返回。
+///
+/// # 参数
+///
+/// * `tts` - 语音合成器。
+/// * `listener` - 已经绑定地址的TCP监听器。
+pub async fn serve_wyoming(tts: Arc<KokoroTts>, listener: TcpListener) -> Result<(), KokoroError> {
+    let mut voices = tts.voice_names().map(ToOwned::to_owned).collect::<Vec<_>>();
+    voices.sort_unstable();
+
+    loop {
+        let (connection, addr) = listener.accept().await?;
+        let tts = tts.clone();
+        let voices = voices.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(connection, voices, |voice| tts.stream(voice)).await {
+                warn!("Wyoming connection {} closed: {}", addr, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{start_synth_session, synthesizer::Synthesized},
+        std::time::Duration,
+        tokio::io::{DuplexStream, duplex},
+    };
+
+    async fn request(
+        client: &mut BufReader<DuplexStream>,
+        event: Event,
+    ) -> Result<Event, KokoroError> {
+        event.write(client.get_mut()).await?;
+        Ok(Event::read(client).await?.expect("Connection closed"))
+    }
+
+    #[tokio::test]
+    async fn test_wyoming() -> Result<(), KokoroError> {
+        let (client, server) = duplex(1 << 16);
+        let voices = vec!["af_heart".to_owned(), "zf_xiaoxiao".to_owned()];
+        tokio::spawn(handle_connection(server, voices, |voice| {
+            // 每句话合成出100个采样的静音
+            start_synth_session(voice, |_, _| async {
+                Ok(Synthesized::new(vec![0.; 100], Duration::ZERO))
+            })
+        }));
+        let mut client = BufReader::new(client);
+
+        let info = request(&mut client, Event::new("describe", json!({}))).await?;
+        assert_eq!("info", info.event_type);
+        let tts = &info.data["tts"][0];
+        assert_eq!("zf_xiaoxiao", tts["voices"][1]["name"]);
+        assert_eq!("zh-cn", tts["voices"][1]["languages"][0]);
+
+        let synthesize = Event::new(
+            "synthesize",
+            json!({"text": "你好。Hello world!", "voice": {"language": "zh"}}),
+        );
+        let start = request(&mut client, synthesize).await?;
+        assert_eq!("audio-start", start.event_type);
+        assert_eq!(24000, start.data["rate"]);
+        for _ in 0..2 {
+            let chunk = Event::read(&mut client).await?.expect("Connection closed");
+            assert_eq!("audio-chunk", chunk.event_type);
+            assert_eq!(200, chunk.payload.len());
+        }
+        let stop = Event::read(&mut client).await?.expect("Connection closed");
+        assert_eq!("audio-stop", stop.event_type);
+
+        let synthesize = Event::new("synthesize", json!({"text": "hi", "voice": {"name": "xx"}}));
+        let error = request(&mut client, synthesize).await?;
+        assert_eq!("error", error.event_type);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_event_limits() {
+        let read = |input: Vec<u8>| async move {
+            let (mut client, server) = duplex(1 << 20);
+            client.write_all(&input).await?;
+            drop(client);
+            Event::read(&mut BufReader::new(server)).await
+        };
+        let header = format!("{}\n", json!({"type": "x", "payload_length": 1u64 << 40}));
+        assert!(matches!(
+            read(header.into_bytes()).await,
+            Err(KokoroError::Io(e)) if e.kind() == ErrorKind::InvalidData
+        ));
+        let header = format!("{{\"type\": \"{}\"}}\n", "x".repeat(1 << 17));
+        assert!(matches!(
+            read(header.into_bytes()).await,
+            Err(KokoroError::Io(e)) if e.kind() == ErrorKind::InvalidData
+        ));
+        let event = read(b"{\"type\": \"describe\"}\n".to_vec()).await;
+        assert_eq!("describe", event.unwrap().unwrap().event_type);
+    }
+}
@@ -0,0 +1,80 @@
+/*
+ * C接口测试：make -C tests/capi [MODEL=kokoro-v1.1-zh.onnx VOICES=voices-v1.1-zh.bin VOICE=zf_001]
+ * 不指定模型时只测试错误处理。
+ */
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+#include "kokoro.h"
+
+static void count_samples(const float *samples, size_t len, void *user_data) {
+    assert(samples != NULL);
+    *(size_t *)user_data += len;
+}
+
+static void test_errors(void) {
+    assert(kokoro_sample_rate() == 24000);
+    assert(kokoro_engine_new("missing.onnx", "missing.bin") == NULL);
+    assert(kokoro_last_error() != NULL && strlen(kokoro_last_error()) > 0);
+    assert(kokoro_engine_new(NULL, "missing.bin") == NULL);
+    assert(strcmp(kokoro_last_error(), "model_path is null") == 0);
+    assert(kokoro_voice_count(NULL) == 0);
+    float *samples = NULL;
+    size_t len = 0;
+    assert(kokoro_synth_alloc(NULL, "你好。", "zf_001", 1.0f, &samples, &len) == KOKORO_STATUS_INVALID_ARGUMENT);
+    kokoro_audio_free(NULL, 0);
+    kokoro_engine_free(NULL);
+    kokoro_stream_stop(NULL);
+}
+
+static void test_synth(const char *model, const char *voices, const char *voice) {
+    KokoroEngine *engine = kokoro_engine_new(model, voices);
+    if (engine == NULL) {
+        fprintf(stderr, "%s\n", kokoro_last_error());
+    }
+    assert(engine != NULL);
+    assert(kokoro_voice_count(engine) > 0);
+    assert(kokoro_voice_name(engine, kokoro_voice_count(engine)) == NULL);
+    printf("%zu voices, first: %s\n", kokoro_voice_count(engine), kokoro_voice_name(engine, 0));
+
+    size_t len = 0;
+    KokoroStatus status = kokoro_synth(engine, "你好。", voice, 1.0f, NULL, 0, &len);
+    assert(status == KOKORO_STATUS_BUFFER_TOO_SMALL && len > 0);
+    static float buffer[24000 * 30];
+    status = kokoro_synth(engine, "你好。", voice, 1.0f, buffer, sizeof(buffer) / sizeof(float), &len);
+    assert(status == KOKORO_STATUS_OK && len > 0);
+    assert(kokoro_synth(engine, "你好。", "missing", 1.0f, buffer, 1, &len) == KOKORO_STATUS_INVALID_ARGUMENT);
+
+    float *samples = NULL;
+    size_t alloc_len = 0;
+    status = kokoro_synth_alloc(engine, "你好。", voice, 1.0f, &samples, &alloc_len);
+    assert(status == KOKORO_STATUS_OK && samples != NULL && alloc_len == len);
+    kokoro_audio_free(samples, alloc_len);
+
+    size_t total = 0;
+    status = kokoro_synth_with_callback(engine, "你好。今天天气很好。", voice, 1.0f, count_samples, &total);
+    assert(status == KOKORO_STATUS_OK && total > 0);
+
+    total = 0;
+    KokoroStream *stream = kokoro_stream_start(engine, voice, 1.0f, count_samples, &total);
+    assert(stream != NULL);
+    assert(kokoro_stream_synth(stream, "你好。今天天气很好。") == KOKORO_STATUS_OK);
+    assert(kokoro_stream_finish(stream) == KOKORO_STATUS_OK);
+    assert(total > 0);
+
+    stream = kokoro_stream_start(engine, voice, 1.0f, count_samples, &total);
+    assert(stream != NULL);
+    assert(kokoro_stream_synth(stream, "这一段会被打断。") == KOKORO_STATUS_OK);
+    kokoro_stream_stop(stream);
+
+    kokoro_engine_free(engine);
+}
+
+int main(int argc, char **argv) {
+    test_errors();
+    if (argc >= 4) {
+        test_synth(argv[1], argv[2], argv[3]);
+    }
+    printf("ok\n");
+    return 0;
+}
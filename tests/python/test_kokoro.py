@@ -0,0 +1,20 @@
+# 安装绑定后运行：pip install . && pytest tests/python
+import pytest
+
+import kokoro_tts
+
+
+def test_g2p():
+    assert kokoro_tts.g2p("hello world") == "həlˈoʊ wˈɜɹld"
+    assert kokoro_tts.G2p().g2p("tomato", variant="british") == "təmˈeɪtəʊ"
+    with pytest.raises(ValueError):
+        kokoro_tts.g2p("hello", variant="klingon")
+
+
+def test_get_token_ids():
+    assert kokoro_tts.get_token_ids("hɛlˈoʊ") == [0, 50, 86, 54, 156, 57, 135, 0]
+
+
+def test_missing_model():
+    with pytest.raises(RuntimeError):
+        kokoro_tts.KokoroTts("missing.onnx", "missing.bin")